@@ -0,0 +1,111 @@
+use std::ops::{Index, IndexMut};
+
+/// Dense storage addressed by `usize` ids, iterated in id order.
+///
+/// Ids of removed values are reused, the most recently freed one first, so
+/// the same sequence of inserts and removes always hands out the same ids.
+pub struct Arena<T> {
+    slots: Vec<Option<T>>,
+    free: Vec<usize>,
+}
+
+impl<T> Arena<T> {
+    pub fn new() -> Arena<T> {
+        Arena {
+            slots: Vec::new(),
+            free: Vec::new(),
+        }
+    }
+
+    pub fn insert(&mut self, value: T) -> usize {
+        match self.free.pop() {
+            Some(id) => {
+                self.slots[id] = Some(value);
+                id
+            }
+            None => {
+                self.slots.push(Some(value));
+                self.slots.len() - 1
+            }
+        }
+    }
+
+    pub fn remove(&mut self, id: usize) -> Option<T> {
+        let value = self.slots.get_mut(id)?.take()?;
+        self.free.push(id);
+        Some(value)
+    }
+
+    pub fn get(&self, id: usize) -> Option<&T> {
+        self.slots.get(id)?.as_ref()
+    }
+
+    pub fn get_mut(&mut self, id: usize) -> Option<&mut T> {
+        self.slots.get_mut(id)?.as_mut()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (usize, &T)> + '_ {
+        self.slots
+            .iter()
+            .enumerate()
+            .filter_map(|(id, slot)| Some((id, slot.as_ref()?)))
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = (usize, &mut T)> + '_ {
+        self.slots
+            .iter_mut()
+            .enumerate()
+            .filter_map(|(id, slot)| Some((id, slot.as_mut()?)))
+    }
+}
+
+impl<T> Default for Arena<T> {
+    fn default() -> Arena<T> {
+        Arena::new()
+    }
+}
+
+impl<T> Index<usize> for Arena<T> {
+    type Output = T;
+
+    fn index(&self, id: usize) -> &T {
+        self.get(id).expect("no value for id")
+    }
+}
+
+impl<T> IndexMut<usize> for Arena<T> {
+    fn index_mut(&mut self, id: usize) -> &mut T {
+        self.get_mut(id).expect("no value for id")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ids_are_reused_in_order() {
+        let mut arena = Arena::new();
+        let ids: Vec<_> = ["a", "b", "c", "d"]
+            .into_iter()
+            .map(|value| arena.insert(value))
+            .collect();
+        assert_eq!(ids, [0, 1, 2, 3]);
+
+        assert_eq!(arena.remove(1), Some("b"));
+        assert_eq!(arena.remove(2), Some("c"));
+        assert_eq!(arena.remove(2), None);
+        assert_eq!(arena.iter().count(), 2);
+
+        assert_eq!(arena.insert("e"), 2);
+        assert_eq!(arena.insert("f"), 1);
+        assert_eq!(arena.insert("g"), 4);
+
+        let values: Vec<_> = arena.iter().collect();
+        assert_eq!(
+            values,
+            [(0, &"a"), (1, &"f"), (2, &"e"), (3, &"d"), (4, &"g")]
+        );
+        assert!(arena.get(5).is_none());
+    }
+}
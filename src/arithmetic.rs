@@ -0,0 +1,448 @@
+//! Arithmetic blocks: adders, a subtractor, a small ALU and a comparator.
+//!
+//! Buses are unsigned with the first bit as the least significant one. Sums
+//! ripple through the bits like in a chain of full adders, so an unknown bit
+//! only spoils the bits above it. A floating carry or borrow input reads as
+//! low, so it can be left unconnected.
+
+use crate::{
+    gates::{DynamicGate, Gate, Params},
+    logic::{bus_value, clamp_width, Logic},
+};
+
+/// A floating carry input is low.
+fn carry_in(value: Logic) -> Logic {
+    match value {
+        Logic::Z => Logic::Zero,
+        value => value,
+    }
+}
+
+/// Sum and carry of a full adder.
+fn full_add(a: Logic, b: Logic, carry: Logic) -> (Logic, Logic) {
+    (a ^ b ^ carry, (a & b) | (carry & (a ^ b)))
+}
+
+/// Adds two buses into `sum`, returns the carry out.
+fn ripple_add(
+    a: &[Logic],
+    b: impl IntoIterator<Item = Logic>,
+    mut carry: Logic,
+    sum: &mut [Logic],
+) -> Logic {
+    for ((sum, a), b) in sum.iter_mut().zip(a).zip(b) {
+        (*sum, carry) = full_add(*a, b, carry);
+    }
+    carry
+}
+
+/// Adds two bits, outputs are the sum and the carry.
+pub struct HalfAdder;
+
+impl Gate<2, 2> for HalfAdder {
+    const NAME: &'static str = "HA";
+    const KIND: &'static str = "half_adder";
+
+    fn update(&self, inputs: &[Logic; 2], outputs: &mut [Logic; 2]) {
+        let (sum, carry) = full_add(inputs[0], inputs[1], Logic::Zero);
+        *outputs = [sum, carry];
+    }
+}
+
+/// Adds two bits and a carry, outputs are the sum and the carry.
+pub struct FullAdder;
+
+impl Gate<3, 2> for FullAdder {
+    const NAME: &'static str = "FA";
+    const KIND: &'static str = "full_adder";
+
+    fn update(&self, inputs: &[Logic; 3], outputs: &mut [Logic; 2]) {
+        let (sum, carry) = full_add(inputs[0], inputs[1], carry_in(inputs[2]));
+        *outputs = [sum, carry];
+    }
+}
+
+/// Adds two buses and a carry, outputs are the sum and the carry.
+pub struct Adder {
+    width: usize,
+}
+
+impl Adder {
+    pub const NAME: &'static str = "ADD";
+    pub const KIND: &'static str = "adder";
+
+    pub fn new(width: usize) -> Adder {
+        Adder {
+            width: clamp_width(width),
+        }
+    }
+}
+
+impl DynamicGate for Adder {
+    fn name(&self) -> &'static str {
+        Self::NAME
+    }
+
+    fn kind(&self) -> &'static str {
+        Self::KIND
+    }
+
+    fn input_widths(&self) -> Vec<usize> {
+        vec![self.width, self.width, 1]
+    }
+
+    fn output_widths(&self) -> Vec<usize> {
+        vec![self.width, 1]
+    }
+
+    fn update(&mut self, inputs: &[Logic], outputs: &mut [Logic]) {
+        let (a, inputs) = inputs.split_at(self.width);
+        let (b, carry) = inputs.split_at(self.width);
+        let (sum, carry_out) = outputs.split_at_mut(self.width);
+        carry_out[0] = ripple_add(a, b.iter().copied(), carry_in(carry[0]), sum);
+    }
+
+    fn params(&self) -> Params {
+        Params::default().with("width", self.width)
+    }
+}
+
+/// Subtracts the second bus and a borrow from the first, outputs are the
+/// difference and the borrow, which is high when the difference wrapped
+/// around.
+pub struct Subtractor {
+    width: usize,
+}
+
+impl Subtractor {
+    pub const NAME: &'static str = "SUB";
+    pub const KIND: &'static str = "subtractor";
+
+    pub fn new(width: usize) -> Subtractor {
+        Subtractor {
+            width: clamp_width(width),
+        }
+    }
+}
+
+impl DynamicGate for Subtractor {
+    fn name(&self) -> &'static str {
+        Self::NAME
+    }
+
+    fn kind(&self) -> &'static str {
+        Self::KIND
+    }
+
+    fn input_widths(&self) -> Vec<usize> {
+        vec![self.width, self.width, 1]
+    }
+
+    fn output_widths(&self) -> Vec<usize> {
+        vec![self.width, 1]
+    }
+
+    fn update(&mut self, inputs: &[Logic], outputs: &mut [Logic]) {
+        // a - b - borrow is a + !b + !borrow
+        let (a, inputs) = inputs.split_at(self.width);
+        let (b, borrow) = inputs.split_at(self.width);
+        let (difference, borrow_out) = outputs.split_at_mut(self.width);
+        let inverted = b.iter().map(|bit| !*bit);
+        borrow_out[0] = !ripple_add(a, inverted, !carry_in(borrow[0]), difference);
+    }
+
+    fn params(&self) -> Params {
+        Params::default().with("width", self.width)
+    }
+}
+
+/// Operation an [`Alu`] performs, selected by its 3 bit opcode input.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AluOp {
+    /// `a + b`, the carry output is the carry out.
+    Add,
+    /// `a - b`, the carry output is the borrow.
+    Sub,
+    And,
+    Or,
+    Xor,
+    /// `!a`, ignores `b`.
+    Not,
+    /// `a` shifted toward the most significant bit, the carry output is the
+    /// bit shifted out.
+    ShiftLeft,
+    /// `a` shifted toward the least significant bit, the carry output is the
+    /// bit shifted out.
+    ShiftRight,
+}
+
+impl AluOp {
+    /// All operations, indexed by their opcode.
+    pub const ALL: [AluOp; 8] = [
+        AluOp::Add,
+        AluOp::Sub,
+        AluOp::And,
+        AluOp::Or,
+        AluOp::Xor,
+        AluOp::Not,
+        AluOp::ShiftLeft,
+        AluOp::ShiftRight,
+    ];
+}
+
+/// Computes one of the [`AluOp`]s of two buses. Inputs are `a`, `b` and the
+/// opcode, outputs the result, the carry and a zero flag which is high when
+/// all bits of the result are low. All outputs are unknown while the opcode
+/// is.
+pub struct Alu {
+    width: usize,
+}
+
+impl Alu {
+    pub const NAME: &'static str = "ALU";
+    pub const KIND: &'static str = "alu";
+
+    pub fn new(width: usize) -> Alu {
+        Alu {
+            width: clamp_width(width),
+        }
+    }
+}
+
+impl DynamicGate for Alu {
+    fn name(&self) -> &'static str {
+        Self::NAME
+    }
+
+    fn kind(&self) -> &'static str {
+        Self::KIND
+    }
+
+    fn input_widths(&self) -> Vec<usize> {
+        vec![self.width, self.width, 3]
+    }
+
+    fn output_widths(&self) -> Vec<usize> {
+        vec![self.width, 1, 1]
+    }
+
+    fn update(&mut self, inputs: &[Logic], outputs: &mut [Logic]) {
+        let width = self.width;
+        let (a, inputs) = inputs.split_at(width);
+        let (b, opcode) = inputs.split_at(width);
+        let (result, flags) = outputs.split_at_mut(width);
+
+        let Some(op) = bus_value(opcode) else {
+            outputs.fill(Logic::X);
+            return;
+        };
+        let bitwise = |result: &mut [Logic], op: fn(Logic, Logic) -> Logic| {
+            for ((result, a), b) in result.iter_mut().zip(a).zip(b) {
+                *result = op(*a, *b);
+            }
+            Logic::Zero
+        };
+        flags[0] = match AluOp::ALL[op as usize] {
+            AluOp::Add => ripple_add(a, b.iter().copied(), Logic::Zero, result),
+            AluOp::Sub => !ripple_add(a, b.iter().map(|bit| !*bit), Logic::One, result),
+            AluOp::And => bitwise(result, |a, b| a & b),
+            AluOp::Or => bitwise(result, |a, b| a | b),
+            AluOp::Xor => bitwise(result, |a, b| a ^ b),
+            AluOp::Not => bitwise(result, |a, _| !a),
+            AluOp::ShiftLeft => {
+                result[0] = Logic::Zero;
+                for (result, a) in result[1..].iter_mut().zip(a) {
+                    *result = a.read();
+                }
+                a[width - 1].read()
+            }
+            AluOp::ShiftRight => {
+                result[width - 1] = Logic::Zero;
+                for (result, a) in result.iter_mut().zip(&a[1..]) {
+                    *result = a.read();
+                }
+                a[0].read()
+            }
+        };
+        flags[1] = result.iter().fold(Logic::One, |zero, bit| zero & !*bit);
+    }
+
+    fn params(&self) -> Params {
+        Params::default().with("width", self.width)
+    }
+}
+
+/// Compares two buses as unsigned numbers, outputs are high when the first
+/// is less than, equal to or greater than the second. All outputs are unknown
+/// while any input bit is.
+pub struct Comparator {
+    width: usize,
+}
+
+impl Comparator {
+    pub const NAME: &'static str = "CMP";
+    pub const KIND: &'static str = "comparator";
+
+    pub fn new(width: usize) -> Comparator {
+        Comparator {
+            width: clamp_width(width),
+        }
+    }
+}
+
+impl DynamicGate for Comparator {
+    fn name(&self) -> &'static str {
+        Self::NAME
+    }
+
+    fn kind(&self) -> &'static str {
+        Self::KIND
+    }
+
+    fn input_widths(&self) -> Vec<usize> {
+        vec![self.width, self.width]
+    }
+
+    fn output_widths(&self) -> Vec<usize> {
+        vec![1, 1, 1]
+    }
+
+    fn update(&mut self, inputs: &[Logic], outputs: &mut [Logic]) {
+        let (a, b) = inputs.split_at(self.width);
+        match (bus_value(a), bus_value(b)) {
+            (Some(a), Some(b)) => {
+                outputs[0] = (a < b).into();
+                outputs[1] = (a == b).into();
+                outputs[2] = (a > b).into();
+            }
+            _ => outputs.fill(Logic::X),
+        }
+    }
+
+    fn params(&self) -> Params {
+        Params::default().with("width", self.width)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gates::tests::*;
+
+    #[test]
+    fn half_adder() {
+        #[rustfmt::skip]
+        let table = TruthTable([
+            ([N, N], [N, N]),
+            ([N, Y], [Y, N]),
+            ([Y, N], [Y, N]),
+            ([Y, Y], [N, Y]),
+            ([X, N], [X, N]),
+            ([Z, Y], [X, X]),
+        ]);
+
+        for row in table.0 {
+            test_gate(HalfAdder, row);
+        }
+    }
+
+    #[test]
+    fn full_adder() {
+        #[rustfmt::skip]
+        let table = TruthTable([
+            ([N, N, N], [N, N]),
+            ([N, Y, N], [Y, N]),
+            ([Y, Y, N], [N, Y]),
+            ([N, N, Y], [Y, N]),
+            ([Y, N, Y], [N, Y]),
+            ([Y, Y, Y], [Y, Y]),
+            ([Y, N, Z], [Y, N]),
+            ([Y, Y, X], [X, Y]),
+            ([N, N, X], [X, N]),
+        ]);
+
+        for row in table.0 {
+            test_gate(FullAdder, row);
+        }
+    }
+
+    #[test]
+    fn adder() {
+        // a, b, carry in -> sum, carry out, least significant bit first
+        #[rustfmt::skip]
+        let table = [
+            ([Y, N, Y, N, Z], [N, Y, N]),
+            ([Y, Y, Y, N, N], [N, N, Y]),
+            ([N, Y, N, Y, Y], [Y, N, Y]),
+            ([X, N, N, N, N], [X, N, N]),
+            ([N, X, Y, N, N], [Y, X, N]),
+        ];
+
+        for row in table {
+            test_dynamic_gate(Adder::new(2), (&row.0, &row.1));
+        }
+    }
+
+    #[test]
+    fn subtractor() {
+        // a, b, borrow in -> difference, borrow out
+        #[rustfmt::skip]
+        let table = [
+            ([Y, Y, N, Y, Z], [Y, N, N]),
+            ([N, Y, N, Y, Y], [Y, Y, Y]),
+            ([N, N, Y, N, N], [Y, Y, Y]),
+            ([Y, N, Y, N, N], [N, N, N]),
+            ([N, Y, X, N, N], [X, X, N]),
+        ];
+
+        for row in table {
+            test_dynamic_gate(Subtractor::new(2), (&row.0, &row.1));
+        }
+    }
+
+    #[test]
+    fn alu() {
+        // a = 0b011, b = 0b110
+        let a = [Y, Y, N];
+        let b = [N, Y, Y];
+        // result, carry, zero for every opcode
+        #[rustfmt::skip]
+        let expected = [
+            [Y, N, N, Y, N], // 3 + 6 = 9
+            [Y, N, Y, Y, N], // 3 - 6 = -3
+            [N, Y, N, N, N],
+            [Y, Y, Y, N, N],
+            [Y, N, Y, N, N],
+            [N, N, Y, N, N],
+            [N, Y, Y, N, N],
+            [Y, N, N, Y, N],
+        ];
+
+        for (op, expected) in expected.iter().enumerate() {
+            let opcode = [0, 1, 2].map(|bit| Logic::from(op >> bit & 1 == 1));
+            let inputs: Vec<_> = a.iter().chain(&b).chain(&opcode).copied().collect();
+            test_dynamic_gate(Alu::new(3), (&inputs, expected));
+        }
+
+        // 0 AND 7 sets the zero flag
+        let inputs = [N, N, N, Y, Y, Y, N, Y, N];
+        test_dynamic_gate(Alu::new(3), (&inputs, &[N, N, N, N, Y]));
+        let inputs = [N, N, N, Y, Y, Y, N, X, N];
+        test_dynamic_gate(Alu::new(3), (&inputs, &[X; 5]));
+    }
+
+    #[test]
+    fn comparator() {
+        #[rustfmt::skip]
+        let table = [
+            ([Y, N, N, Y], [Y, N, N]),
+            ([Y, Y, Y, Y], [N, Y, N]),
+            ([N, Y, Y, N], [N, N, Y]),
+            ([N, Y, Z, N], [X, X, X]),
+        ];
+
+        for row in table {
+            test_dynamic_gate(Comparator::new(2), (&row.0, &row.1));
+        }
+    }
+}
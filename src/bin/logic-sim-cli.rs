@@ -0,0 +1,12 @@
+//! The command line interface of [`logic_sim::cli`] on its own, it builds
+//! without the `gui` feature and needs no display or audio libraries.
+
+use logic_sim::{cli, registry::GateRegistry};
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if let Err(err) = cli::run(&args, &GateRegistry::default()) {
+        eprintln!("{err}");
+        std::process::exit(err.exit_code());
+    }
+}
@@ -0,0 +1,564 @@
+//! Text format of saved circuits.
+//!
+//! A circuit file is a list of lines, each starting with a keyword followed
+//! by values separated by whitespace. Empty lines and lines starting with `#`
+//! are ignored. The first non-ignored line declares the format version:
+//!
+//! ```text
+//! logic-sim 4
+//! ```
+//!
+//! Inputs driven by several outputs are wired-OR, unless another
+//! [`NetMode`] is chosen:
+//!
+//! ```text
+//! nets contention
+//! ```
+//!
+//! Gates are declared with an id unique within the file, the stable kind of
+//! the gate, its position and optional `key=value` parameters:
+//!
+//! ```text
+//! gate 0 clock 120 80 period=10 duty_cycle=0.5
+//! gate 1 led 300 80
+//! ```
+//!
+//! Values containing whitespace, quotes, backslashes or control characters are
+//! written in double quotes with the escapes of Rust string literals, like
+//! `file="roms/boot loader.hex"`.
+//!
+//! Every gate accepts a `delay` parameter, the propagation delay in ticks,
+//! which is written only when it differs from the delay of the gate kind.
+//!
+//! Connections go from an output pin to an input pin, each given by the gate
+//! id, the pin index and the offset of the pin from the gate position:
+//!
+//! ```text
+//! connection 0 0 80 22.5 1 0 0 22.5
+//! ```
+//!
+//! Custom components are declared before the gates which use them, as a block
+//! with a net mode, gates and connections of their own, followed by the ids of
+//! the gates acting as the component inputs and outputs in order. Components
+//! can use other components declared before them:
+//!
+//! ```text
+//! component buffer
+//! gate 0 switch 0 0 on=false
+//! gate 1 led 100 0
+//! connection 0 0 80 22.5 1 0 0 22.5
+//! input 0
+//! output 1
+//! end
+//! gate 2 component 300 80 name=buffer
+//! ```
+//!
+//! Gates are written ordered by id and connections in the order they were
+//! made, so saving the same board twice gives the same file.
+
+use std::fmt;
+
+use crate::{
+    gates::Params,
+    logic_simulation::{NetMode, SimError},
+};
+
+/// Version written to new files, all versions up to this one can be read.
+///
+/// Version 2 added custom components, version 3 net modes and version 4
+/// quoted values.
+pub const VERSION: u32 = 4;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct GateEntry {
+    pub id: usize,
+    pub kind: String,
+    pub pos: (f32, f32),
+    pub params: Params,
+}
+
+/// Gate id, pin index and offset of the pin from the gate position.
+pub type PinEntry = (usize, usize, (f32, f32));
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConnectionEntry {
+    pub output: PinEntry,
+    pub input: PinEntry,
+}
+
+/// Contents of a circuit file, independent of any running simulation.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Circuit {
+    /// Custom components, always empty for the circuit of a component.
+    pub components: Vec<ComponentDef>,
+    pub nets: NetMode,
+    pub gates: Vec<GateEntry>,
+    pub connections: Vec<ConnectionEntry>,
+}
+
+/// A named sub-circuit which can be placed as a single gate. The component
+/// inputs drive the outputs of the listed source gates and the component
+/// outputs read the inputs of the listed sink gates, both by gate id.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ComponentDef {
+    pub name: String,
+    pub circuit: Circuit,
+    pub inputs: Vec<usize>,
+    pub outputs: Vec<usize>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum LoadError {
+    /// The line can not be parsed, lines are numbered from 1.
+    Syntax {
+        line: usize,
+        message: String,
+    },
+    UnsupportedVersion(u32),
+    UnknownKind(String),
+    InvalidParams {
+        kind: String,
+        message: String,
+    },
+    UnknownGate(usize),
+    InvalidConnection(SimError),
+    /// Component input or output which is not a gate with an output or an
+    /// input respectively.
+    InvalidComponentPin {
+        component: String,
+        gate: usize,
+    },
+}
+
+impl fmt::Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LoadError::Syntax { line, message } => write!(f, "line {line}: {message}"),
+            LoadError::UnsupportedVersion(version) => {
+                write!(f, "unsupported file version {version}")
+            }
+            LoadError::UnknownKind(kind) => write!(f, "unknown gate kind '{kind}'"),
+            LoadError::InvalidParams { kind, message } => write!(f, "{kind}: {message}"),
+            LoadError::UnknownGate(id) => write!(f, "connection to unknown gate {id}"),
+            LoadError::InvalidConnection(err) => write!(f, "invalid connection: {err}"),
+            LoadError::InvalidComponentPin { component, gate } => {
+                write!(f, "gate {gate} can not be a pin of component '{component}'")
+            }
+        }
+    }
+}
+
+impl std::error::Error for LoadError {}
+
+impl Circuit {
+    pub fn parse(source: &str) -> Result<Circuit, LoadError> {
+        let mut circuit = Circuit::default();
+        let mut version = None;
+        let mut component: Option<ComponentDef> = None;
+
+        for (index, line) in source.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let syntax = |message: &str| LoadError::Syntax {
+                line: index + 1,
+                message: message.to_owned(),
+            };
+
+            let words =
+                split(line, version.is_some_and(|version| version >= 4)).map_err(&syntax)?;
+            let mut words = words.iter().map(String::as_str);
+            let keyword = words.next().unwrap_or_default();
+
+            let mut next = |what: &str| {
+                words
+                    .next()
+                    .ok_or_else(|| syntax(&format!("missing {what}")))
+            };
+
+            if version.is_none() {
+                if keyword != "logic-sim" {
+                    return Err(syntax("expected 'logic-sim <version>' header"));
+                }
+                let value = parse(next("version")?, "version", &syntax)?;
+                if !(1..=VERSION).contains(&value) {
+                    return Err(LoadError::UnsupportedVersion(value));
+                }
+                version = Some(value);
+                continue;
+            }
+
+            let target = match &mut component {
+                Some(component) => &mut component.circuit,
+                None => &mut circuit,
+            };
+
+            match keyword {
+                "nets" => {
+                    let mode = next("net mode")?;
+                    target.nets = mode
+                        .parse()
+                        .map_err(|_| syntax(&format!("unknown net mode '{mode}'")))?;
+                }
+                "gate" => {
+                    let id = parse(next("gate id")?, "gate id", &syntax)?;
+                    let kind = next("gate kind")?.to_owned();
+                    let x = parse(next("x position")?, "x position", &syntax)?;
+                    let y = parse(next("y position")?, "y position", &syntax)?;
+
+                    let mut params = Params::default();
+                    for param in words {
+                        let (key, value) = param
+                            .split_once('=')
+                            .ok_or_else(|| syntax("expected 'key=value' parameter"))?;
+                        params.set(key, value);
+                    }
+
+                    target.gates.push(GateEntry {
+                        id,
+                        kind,
+                        pos: (x, y),
+                        params,
+                    });
+                }
+                "connection" => {
+                    let mut pin = || -> Result<PinEntry, LoadError> {
+                        Ok((
+                            parse(next("gate id")?, "gate id", &syntax)?,
+                            parse(next("pin index")?, "pin index", &syntax)?,
+                            (
+                                parse(next("x offset")?, "x offset", &syntax)?,
+                                parse(next("y offset")?, "y offset", &syntax)?,
+                            ),
+                        ))
+                    };
+                    let output = pin()?;
+                    let input = pin()?;
+
+                    if words.next().is_some() {
+                        return Err(syntax("unexpected values after connection"));
+                    }
+
+                    target.connections.push(ConnectionEntry { output, input });
+                }
+                "component" if component.is_none() => {
+                    let name = next("component name")?.to_owned();
+                    component = Some(ComponentDef {
+                        name,
+                        circuit: Circuit::default(),
+                        inputs: Vec::new(),
+                        outputs: Vec::new(),
+                    });
+                }
+                "input" | "output" => {
+                    let component = component
+                        .as_mut()
+                        .ok_or_else(|| syntax(&format!("'{keyword}' outside of component")))?;
+                    let id = parse(next("gate id")?, "gate id", &syntax)?;
+                    match keyword {
+                        "input" => component.inputs.push(id),
+                        _ => component.outputs.push(id),
+                    }
+                }
+                "end" => {
+                    let component = component
+                        .take()
+                        .ok_or_else(|| syntax("'end' outside of component"))?;
+                    circuit.components.push(component);
+                }
+                keyword => return Err(syntax(&format!("unknown keyword '{keyword}'"))),
+            }
+        }
+
+        if version.is_none() {
+            return Err(LoadError::Syntax {
+                line: 0,
+                message: "empty file".to_owned(),
+            });
+        }
+
+        if let Some(component) = component {
+            return Err(LoadError::Syntax {
+                line: source.lines().count(),
+                message: format!("component '{}' is missing 'end'", component.name),
+            });
+        }
+
+        Ok(circuit)
+    }
+
+    fn write_body(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.nets != NetMode::default() {
+            writeln!(f, "nets {}", self.nets.as_str())?;
+        }
+
+        for gate in &self.gates {
+            write!(
+                f,
+                "gate {} {} {} {}",
+                gate.id, gate.kind, gate.pos.0, gate.pos.1
+            )?;
+            for (key, value) in gate.params.iter() {
+                write!(f, " {key}={}", Word(value))?;
+            }
+            writeln!(f)?;
+        }
+
+        for ConnectionEntry { output, input } in &self.connections {
+            writeln!(
+                f,
+                "connection {} {} {} {} {} {} {} {}",
+                output.0,
+                output.1,
+                output.2 .0,
+                output.2 .1,
+                input.0,
+                input.1,
+                input.2 .0,
+                input.2 .1
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+fn parse<T: std::str::FromStr>(
+    word: &str,
+    what: &str,
+    syntax: &impl Fn(&str) -> LoadError,
+) -> Result<T, LoadError> {
+    word.parse()
+        .map_err(|_| syntax(&format!("invalid {what} '{word}'")))
+}
+
+/// Splits a line into words, which can be quoted when `quoted` is set.
+fn split(line: &str, quoted: bool) -> Result<Vec<String>, &'static str> {
+    if !quoted {
+        return Ok(line.split_whitespace().map(str::to_owned).collect());
+    }
+
+    let mut words = Vec::new();
+    let mut word: Option<String> = None;
+    let mut chars = line.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            c if c.is_whitespace() => words.extend(word.take()),
+            '"' => {
+                let word = word.get_or_insert_with(String::new);
+                loop {
+                    match chars.next().ok_or("missing closing quote")? {
+                        '"' => break,
+                        '\\' => word.push(unescape(&mut chars)?),
+                        c => word.push(c),
+                    }
+                }
+            }
+            c => word.get_or_insert_with(String::new).push(c),
+        }
+    }
+    words.extend(word);
+    Ok(words)
+}
+
+/// Character of an escape sequence whose backslash was already read.
+fn unescape(chars: &mut std::str::Chars) -> Result<char, &'static str> {
+    Ok(match chars.next().ok_or("missing closing quote")? {
+        'n' => '\n',
+        'r' => '\r',
+        't' => '\t',
+        '0' => '\0',
+        c @ ('\\' | '"' | '\'') => c,
+        'u' => {
+            let code = chars
+                .as_str()
+                .strip_prefix('{')
+                .and_then(|rest| rest.split_once('}'))
+                .and_then(|(code, _)| u32::from_str_radix(code, 16).ok())
+                .and_then(char::from_u32)
+                .ok_or("invalid unicode escape")?;
+            chars.find(|&c| c == '}');
+            code
+        }
+        _ => return Err("invalid escape"),
+    })
+}
+
+/// Word of a line, quoted when it could not be read back otherwise.
+struct Word<'a>(&'a str);
+
+impl fmt::Display for Word<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let quote = |c: char| c.is_whitespace() || c.is_control() || c == '"' || c == '\\';
+        if self.0.contains(quote) {
+            write!(f, "{:?}", self.0)
+        } else {
+            f.write_str(self.0)
+        }
+    }
+}
+
+impl fmt::Display for Circuit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "logic-sim {VERSION}")?;
+
+        for component in &self.components {
+            writeln!(f, "component {}", Word(&component.name))?;
+            component.circuit.write_body(f)?;
+            for input in &component.inputs {
+                writeln!(f, "input {input}")?;
+            }
+            for output in &component.outputs {
+                writeln!(f, "output {output}")?;
+            }
+            writeln!(f, "end")?;
+        }
+
+        self.write_body(f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SOURCE: &str = "\
+# two gates
+logic-sim 1
+
+gate 0 clock 120 80 period=10 duty_cycle=0.5
+gate 3 led 300.5 80
+connection 0 0 80 22.5 3 0 0 22.5
+";
+
+    #[test]
+    fn round_trip() {
+        let circuit = Circuit::parse(SOURCE).unwrap();
+
+        assert_eq!(circuit.gates.len(), 2);
+        assert_eq!(circuit.gates[1].id, 3);
+        assert_eq!(circuit.gates[1].pos, (300.5, 80.));
+        assert_eq!(circuit.gates[0].params.get("period"), Some("10"));
+        assert_eq!(
+            circuit.connections,
+            vec![ConnectionEntry {
+                output: (0, 0, (80., 22.5)),
+                input: (3, 0, (0., 22.5)),
+            }]
+        );
+
+        let saved = circuit.to_string();
+        assert_eq!(Circuit::parse(&saved).unwrap(), circuit);
+        assert_eq!(saved, Circuit::parse(&saved).unwrap().to_string());
+    }
+
+    #[test]
+    fn errors() {
+        assert!(matches!(
+            Circuit::parse("logic-sim 5"),
+            Err(LoadError::UnsupportedVersion(5))
+        ));
+        assert!(matches!(
+            Circuit::parse("logic-sim 3\nnets wired_xor"),
+            Err(LoadError::Syntax { line: 2, .. })
+        ));
+        assert!(matches!(
+            Circuit::parse("gate 0 and 0 0"),
+            Err(LoadError::Syntax { line: 1, .. })
+        ));
+        assert!(matches!(
+            Circuit::parse("logic-sim 1\ngate 0 and 0"),
+            Err(LoadError::Syntax { line: 2, .. })
+        ));
+        assert!(matches!(
+            Circuit::parse("logic-sim 1\nwire 0 0 1 0"),
+            Err(LoadError::Syntax { line: 2, .. })
+        ));
+        assert!(matches!(Circuit::parse(""), Err(LoadError::Syntax { .. })));
+        assert!(matches!(
+            Circuit::parse("logic-sim 2\ninput 0"),
+            Err(LoadError::Syntax { line: 2, .. })
+        ));
+        assert!(matches!(
+            Circuit::parse("logic-sim 2\ncomponent a\ngate 0 and 0 0"),
+            Err(LoadError::Syntax { line: 3, .. })
+        ));
+        assert!(matches!(
+            Circuit::parse("logic-sim 2\ncomponent a\ncomponent b"),
+            Err(LoadError::Syntax { line: 3, .. })
+        ));
+        assert!(matches!(
+            Circuit::parse("logic-sim 4\ngate 0 rom 0 0 file=\"a b"),
+            Err(LoadError::Syntax { line: 2, .. })
+        ));
+        assert!(matches!(
+            Circuit::parse("logic-sim 4\ngate 0 rom 0 0 file=\"\\q\""),
+            Err(LoadError::Syntax { line: 2, .. })
+        ));
+    }
+
+    #[test]
+    fn quoted_values() {
+        let mut circuit = Circuit::default();
+        for (id, file) in [
+            "roms/boot loader.hex",
+            "tab\there",
+            "\"quoted\" \\ \u{7f}",
+            "",
+        ]
+        .into_iter()
+        .enumerate()
+        {
+            circuit.gates.push(GateEntry {
+                id,
+                kind: "rom".to_owned(),
+                pos: (0., 0.),
+                params: Params::default().with("file", file).with("width", 8),
+            });
+        }
+
+        let saved = circuit.to_string();
+        assert!(saved.contains("gate 0 rom 0 0 file=\"roms/boot loader.hex\" width=8\n"));
+        assert_eq!(saved.lines().count(), 5);
+        assert_eq!(Circuit::parse(&saved).unwrap(), circuit);
+
+        // Quotes had no meaning before version 4.
+        let old = Circuit::parse("logic-sim 3\ngate 0 rom 0 0 file=\"a\"").unwrap();
+        assert_eq!(old.gates[0].params.get("file"), Some("\"a\""));
+    }
+
+    #[test]
+    fn components() {
+        let source = "\
+logic-sim 4
+component buffer
+nets contention
+gate 0 switch 0 0 on=false
+gate 1 led 100 0
+connection 0 0 80 22.5 1 0 0 22.5
+input 0
+output 1
+end
+gate 2 component 300 80 name=buffer
+";
+        let circuit = Circuit::parse(source).unwrap();
+
+        assert_eq!(circuit.gates.len(), 1);
+        assert_eq!(circuit.components.len(), 1);
+
+        let component = &circuit.components[0];
+        assert_eq!(component.name, "buffer");
+        assert_eq!(component.circuit.nets, NetMode::Contention);
+        assert_eq!(circuit.nets, NetMode::WiredOr);
+        assert_eq!(component.circuit.gates.len(), 2);
+        assert_eq!(component.circuit.connections.len(), 1);
+        assert_eq!(
+            (&component.inputs[..], &component.outputs[..]),
+            (&[0][..], &[1][..])
+        );
+
+        assert_eq!(circuit.to_string(), source);
+    }
+}
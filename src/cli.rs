@@ -0,0 +1,395 @@
+//! Headless runner which applies input vectors to a saved circuit and prints
+//! the outputs, so circuits can be checked without a display:
+//!
+//! ```text
+//! logic-sim run <circuit> <vectors> [--ticks N] [--output FILE] [--vcd FILE]
+//! ```
+//!
+//! Inputs of the circuit are its switches and buttons and its outputs are its
+//! leds, both ordered top to bottom like the pins of a custom component. The
+//! vectors file has one vector per line with a `0` or `1` for every input,
+//! whitespace within a vector, empty lines and lines starting with `#` are
+//! ignored:
+//!
+//! ```text
+//! # a b
+//! 0 1
+//! 1 1
+//! ```
+//!
+//! After each vector is applied the circuit runs for N ticks, or until it is
+//! stable when no tick count is given, and a line with the vector and the
+//! values of the outputs is printed:
+//!
+//! ```text
+//! 01 -> 10
+//! 11 -> 01
+//! ```
+//!
+//! With `--vcd` the inputs and outputs are also recorded as a value change
+//! dump, see [`crate::vcd`], with a scope for each of their gates named by
+//! kind and id in the circuit file, like `logic_sim.led.led4`.
+//!
+//! `logic-sim gates` lists the gate kinds circuit files can use, by category
+//! with the names of their pins.
+//!
+//! Both commands are also run by the `logic-sim-cli` binary, which builds
+//! without the `gui` feature.
+
+use std::{fmt, fs, io};
+
+use crate::{
+    circuit_file::{Circuit, LoadError},
+    component,
+    logic_simulation::{LogicSimulation, Oscillation},
+    probe::ProbePin,
+    registry::GateRegistry,
+    vcd,
+};
+
+pub const USAGE: &str = "\
+usage: logic-sim run <circuit> <vectors> [--ticks N] [--output FILE] [--vcd FILE]
+       logic-sim gates";
+
+#[derive(Debug)]
+pub enum CliError {
+    Usage(String),
+    Io {
+        path: String,
+        error: io::Error,
+    },
+    Load(LoadError),
+    /// The vector can not be parsed, lines are numbered from 1.
+    Vector {
+        line: usize,
+        message: String,
+    },
+    /// The circuit did not become stable after the vector on the line.
+    Oscillation {
+        line: usize,
+        oscillation: Oscillation,
+    },
+}
+
+impl fmt::Display for CliError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CliError::Usage(message) => write!(f, "{message}\n{USAGE}"),
+            CliError::Io { path, error } => write!(f, "{path}: {error}"),
+            CliError::Load(err) => write!(f, "invalid circuit: {err}"),
+            CliError::Vector { line, message } => write!(f, "vector on line {line}: {message}"),
+            CliError::Oscillation { line, oscillation } => {
+                write!(f, "vector on line {line}: {oscillation}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for CliError {}
+
+impl CliError {
+    /// Status the process exits with, 2 for usage errors and 1 otherwise.
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            CliError::Usage(_) => 2,
+            _ => 1,
+        }
+    }
+}
+
+/// Runs the command given by the arguments, without the program name.
+/// Circuits can use the gate kinds of the registry.
+pub fn run(args: &[String], registry: &GateRegistry) -> Result<(), CliError> {
+    let usage = |message: &str| CliError::Usage(message.to_owned());
+
+    let mut args = args.iter();
+    match args.next().map(String::as_str) {
+        Some("run") => {}
+        Some("gates") => {
+            if let Some(arg) = args.next() {
+                return Err(usage(&format!("unexpected argument '{arg}'")));
+            }
+            print!("{}", gate_list(registry));
+            return Ok(());
+        }
+        Some(command) => return Err(usage(&format!("unknown command '{command}'"))),
+        None => return Err(usage("missing command")),
+    }
+
+    let mut paths = Vec::new();
+    let mut ticks = None;
+    let mut output = None;
+    let mut vcd_path = None;
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--ticks" => {
+                let value = args.next().ok_or_else(|| usage("missing tick count"))?;
+                let value = value
+                    .parse()
+                    .map_err(|_| usage(&format!("invalid tick count '{value}'")))?;
+                ticks = Some(value);
+            }
+            "--output" => output = Some(args.next().ok_or_else(|| usage("missing output file"))?),
+            "--vcd" => vcd_path = Some(args.next().ok_or_else(|| usage("missing vcd file"))?),
+            option if option.starts_with("--") => {
+                return Err(usage(&format!("unknown option '{option}'")))
+            }
+            path => paths.push(path),
+        }
+    }
+
+    let [circuit_path, vectors_path] = paths[..] else {
+        return Err(usage("expected a circuit file and a vectors file"));
+    };
+
+    let read = |path: &str| {
+        fs::read_to_string(path).map_err(|error| CliError::Io {
+            path: path.to_owned(),
+            error,
+        })
+    };
+    let circuit = Circuit::parse(&read(circuit_path)?).map_err(CliError::Load)?;
+    let mut vcd = vcd_path.map(|_| Vec::new());
+    let vectors = read(vectors_path)?;
+    let report = run_vectors(&circuit, registry, &vectors, ticks, vcd.as_mut())?;
+
+    let write = |path: &str, contents: &[u8]| {
+        fs::write(path, contents).map_err(|error| CliError::Io {
+            path: path.to_owned(),
+            error,
+        })
+    };
+    if let (Some(path), Some(vcd)) = (vcd_path, vcd) {
+        write(path, &vcd)?;
+    }
+    match output {
+        Some(path) => write(path, report.as_bytes()),
+        None => {
+            print!("{report}");
+            Ok(())
+        }
+    }
+}
+
+/// Lists the kinds of the registry under their categories, each with its
+/// name and the names of its pins. The last pin name of a gate repeats when
+/// it has more pins, see [`crate::registry::GateInfo::inputs`].
+pub fn gate_list(registry: &GateRegistry) -> String {
+    let mut list = String::new();
+    for category in registry.categories() {
+        list.push_str(category);
+        list.push('\n');
+        for info in registry.iter().filter(|info| info.category == category) {
+            list.push_str(&format!(
+                "  {:<18}{:<7}{} -> {}\n",
+                info.kind,
+                info.name,
+                info.inputs.join(", "),
+                info.outputs.join(", ")
+            ));
+        }
+    }
+    list
+}
+
+/// Applies the vectors one after another and returns a line with the vector
+/// and the outputs for each of them. Runs `ticks` ticks after each vector, or
+/// until the circuit is stable. The inputs and outputs are recorded into
+/// `vcd` when given.
+pub fn run_vectors(
+    circuit: &Circuit,
+    registry: &GateRegistry,
+    vectors: &str,
+    ticks: Option<usize>,
+    vcd: Option<&mut Vec<u8>>,
+) -> Result<String, CliError> {
+    let mut sim = LogicSimulation::new();
+    let ids = component::instantiate(circuit, registry, &circuit.components, &mut sim)
+        .map_err(CliError::Load)?;
+    let (inputs, outputs) = component::pins(circuit);
+
+    if vcd.is_some() {
+        // all gates of the circuit are in the simulation
+        for input in &inputs {
+            let gate = ids[input];
+            sim.add_probe(ProbePin::Output { gate, output: 0 }).unwrap();
+        }
+        for output in &outputs {
+            let gate = ids[output];
+            sim.add_probe(ProbePin::Input { gate, input: 0 }).unwrap();
+        }
+    }
+
+    let mut report = String::new();
+    for (index, line) in vectors.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let vector_error = |message: String| CliError::Vector {
+            line: index + 1,
+            message,
+        };
+
+        let vector = line
+            .chars()
+            .filter(|c| !c.is_whitespace())
+            .map(|c| match c {
+                '0' => Ok(false),
+                '1' => Ok(true),
+                c => Err(vector_error(format!("invalid value '{c}'"))),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        if vector.len() != inputs.len() {
+            return Err(vector_error(format!(
+                "expected {} values, found {}",
+                inputs.len(),
+                vector.len()
+            )));
+        }
+
+        component::drive_inputs(&mut sim, &ids, &inputs, &vector);
+
+        match ticks {
+            Some(ticks) => {
+                for _ in 0..ticks {
+                    sim.simulate();
+                }
+            }
+            None => {
+                sim.settle(component::MAX_SETTLE_TICKS)
+                    .map_err(|oscillation| CliError::Oscillation {
+                        line: index + 1,
+                        oscillation,
+                    })?;
+            }
+        }
+
+        let vector: String = vector
+            .iter()
+            .map(|value| if *value { '1' } else { '0' })
+            .collect();
+        report.push_str(&vector);
+        report.push_str(" ->");
+        if !outputs.is_empty() {
+            report.push(' ');
+        }
+        for value in component::read_outputs(&sim, &ids, &outputs) {
+            report.push_str(&value.to_string());
+        }
+        report.push('\n');
+    }
+
+    if let Some(vcd) = vcd {
+        let scope = |id| {
+            let gate = circuit
+                .gates
+                .iter()
+                .find(|gate| ids[&gate.id] == id)
+                .unwrap();
+            format!("{}{}", gate.kind, gate.id)
+        };
+        vcd::write(&sim, registry, &scope, vcd).expect("writing to memory does not fail");
+    }
+
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::component::tests::HALF_ADDER;
+
+    const VECTORS: &str = "\
+# a b
+0 0
+0 1
+
+1 0
+11
+";
+
+    #[test]
+    fn vectors() {
+        let circuit = Circuit::parse(HALF_ADDER).unwrap();
+        let expected = "00 -> 00\n01 -> 10\n10 -> 10\n11 -> 01\n";
+        assert_eq!(
+            run_vectors(&circuit, &GateRegistry::default(), VECTORS, None, None).unwrap(),
+            expected
+        );
+        assert_eq!(
+            run_vectors(&circuit, &GateRegistry::default(), VECTORS, Some(3), None).unwrap(),
+            expected
+        );
+
+        // outputs have not been reached yet after a single tick
+        let report = run_vectors(&circuit, &GateRegistry::default(), "11", Some(1), None).unwrap();
+        assert_eq!(report, "11 -> XX\n");
+    }
+
+    #[test]
+    fn vcd() {
+        let circuit = Circuit::parse(HALF_ADDER).unwrap();
+        let mut vcd = Vec::new();
+        run_vectors(
+            &circuit,
+            &GateRegistry::default(),
+            "01\n11",
+            None,
+            Some(&mut vcd),
+        )
+        .unwrap();
+
+        let vcd = String::from_utf8(vcd).unwrap();
+        for line in [
+            "$scope module led $end",
+            "$scope module led5 $end",
+            "$var wire 1 # in $end",
+        ] {
+            assert!(vcd.contains(line), "{vcd}");
+        }
+    }
+
+    #[test]
+    fn invalid_vectors() {
+        let circuit = Circuit::parse(HALF_ADDER).unwrap();
+        assert!(matches!(
+            run_vectors(
+                &circuit,
+                &GateRegistry::default(),
+                "# a b\n0 1 1",
+                None,
+                None
+            ),
+            Err(CliError::Vector { line: 2, .. })
+        ));
+        assert!(matches!(
+            run_vectors(&circuit, &GateRegistry::default(), "0 x", None, None),
+            Err(CliError::Vector { line: 1, .. })
+        ));
+    }
+
+    #[test]
+    fn usage() {
+        let args = |args: &[&str]| args.iter().map(|arg| arg.to_string()).collect::<Vec<_>>();
+        for args in [
+            args(&[]),
+            args(&["simulate"]),
+            args(&["run", "circuit.txt"]),
+            args(&["run", "circuit.txt", "vectors.txt", "--ticks", "many"]),
+            args(&["run", "circuit.txt", "vectors.txt", "--verbose"]),
+        ] {
+            let registry = GateRegistry::default();
+            assert!(matches!(run(&args, &registry), Err(CliError::Usage(_))));
+        }
+    }
+
+    #[test]
+    fn gates() {
+        let list = gate_list(&GateRegistry::default());
+        assert!(list.starts_with("Gates\n  and               AND    in -> out\n"));
+        assert!(list.contains("\nMemory\n"));
+        assert!(list.contains("  multiplexer       MUX    select, in -> out\n"));
+    }
+}
@@ -0,0 +1,496 @@
+use std::{collections::HashMap, fmt};
+
+use crate::{
+    circuit_file::{Circuit, ComponentDef, ConnectionEntry, GateEntry, LoadError},
+    gates::{Button, Gate, Led, ParamError, Params, Source, Switch},
+    logic::Logic,
+    logic_simulation::{BoxedGate, Component, LogicSimulation, SimError},
+    registry::GateRegistry,
+};
+
+/// Kind of every custom component, the component itself is chosen by the
+/// `name` parameter.
+pub const KIND: &str = "component";
+
+/// Kinds of the gates which become inputs of a component.
+const INPUT_KINDS: [&str; 2] = [Switch::KIND, Button::KIND];
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ComponentError {
+    /// Names must be non empty and can not contain whitespace or `=`, so they
+    /// can be stored in circuit files.
+    InvalidName(String),
+    DuplicateName(String),
+    /// A component needs at least one led acting as its output.
+    NoOutputs,
+}
+
+impl fmt::Display for ComponentError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ComponentError::InvalidName(name) => write!(f, "invalid component name '{name}'"),
+            ComponentError::DuplicateName(name) => {
+                write!(f, "component '{name}' already exists")
+            }
+            ComponentError::NoOutputs => write!(f, "component needs at least one led as output"),
+        }
+    }
+}
+
+impl std::error::Error for ComponentError {}
+
+/// Makes a component from the circuit, switches and buttons become its inputs
+/// and leds its outputs, both ordered top to bottom by their position.
+pub fn define(
+    name: &str,
+    circuit: Circuit,
+    library: &[ComponentDef],
+) -> Result<ComponentDef, ComponentError> {
+    if name.is_empty() || name.contains(|c: char| c.is_whitespace() || c == '=') {
+        return Err(ComponentError::InvalidName(name.to_owned()));
+    }
+
+    if library.iter().any(|component| component.name == name) {
+        return Err(ComponentError::DuplicateName(name.to_owned()));
+    }
+
+    let (inputs, outputs) = pins(&circuit);
+
+    if outputs.is_empty() {
+        return Err(ComponentError::NoOutputs);
+    }
+
+    Ok(ComponentDef {
+        name: name.to_owned(),
+        circuit,
+        inputs,
+        outputs,
+    })
+}
+
+/// Ids of the switches and buttons, and of the leds of the circuit, both
+/// ordered top to bottom by their position.
+pub fn pins(circuit: &Circuit) -> (Vec<usize>, Vec<usize>) {
+    let pins = |kinds: &[&str]| {
+        let mut gates: Vec<_> = circuit
+            .gates
+            .iter()
+            .filter(|gate| kinds.contains(&gate.kind.as_str()))
+            .collect();
+        gates.sort_by(|a, b| {
+            a.pos
+                .1
+                .total_cmp(&b.pos.1)
+                .then(a.pos.0.total_cmp(&b.pos.0))
+        });
+        gates.into_iter().map(|gate| gate.id).collect::<Vec<_>>()
+    };
+
+    (pins(&INPUT_KINDS), pins(&[Led::KIND]))
+}
+
+/// Sets a switch or button of an instantiated circuit, one of the inputs
+/// returned by [`pins`], to the value.
+pub fn drive(sim: &mut LogicSimulation, id: usize, value: bool) -> Result<(), SimError> {
+    if sim.get_gate_kind(id)? == Switch::KIND {
+        // switches toggle with every press
+        if sim.get_gate_params(id)?.get_or("on", false) != Ok(value) {
+            sim.press(id)?;
+        }
+        Ok(())
+    } else if value {
+        sim.press(id)
+    } else {
+        sim.release(id)
+    }
+}
+
+/// Ticks an instantiated circuit gets to become stable after its inputs are
+/// driven, see [`LogicSimulation::settle`].
+pub const MAX_SETTLE_TICKS: usize = 10_000;
+
+/// Drives the inputs of a circuit instantiated with [`instantiate`], `ids`
+/// are the ids it returned and `inputs` the inputs returned by [`pins`].
+pub fn drive_inputs(
+    sim: &mut LogicSimulation,
+    ids: &HashMap<usize, usize>,
+    inputs: &[usize],
+    values: &[bool],
+) {
+    for (input, value) in inputs.iter().zip(values) {
+        // all gates of the circuit are in the simulation
+        drive(sim, ids[input], *value).unwrap();
+    }
+}
+
+/// Values of the outputs returned by [`pins`] of an instantiated circuit.
+pub fn read_outputs(
+    sim: &LogicSimulation,
+    ids: &HashMap<usize, usize>,
+    outputs: &[usize],
+) -> Vec<Logic> {
+    outputs
+        .iter()
+        .map(|output| sim.get_input(ids[output], 0).unwrap()[0])
+        .collect()
+}
+
+/// Adds all gates and connections of the circuit to the simulation and takes
+/// over its net mode, gates of [`KIND`] are looked up by name in `library`
+/// and all others in `registry`. Returns the simulation ids of the gates
+/// keyed by their ids in the circuit.
+pub fn instantiate(
+    circuit: &Circuit,
+    registry: &GateRegistry,
+    library: &[ComponentDef],
+    sim: &mut LogicSimulation,
+) -> Result<HashMap<usize, usize>, LoadError> {
+    instantiate_with(circuit, sim, |gate| {
+        create(&gate.kind, &gate.params, registry, library)
+    })
+}
+
+/// Like [`instantiate`], with gates made by `create`.
+fn instantiate_with(
+    circuit: &Circuit,
+    sim: &mut LogicSimulation,
+    mut create: impl FnMut(&GateEntry) -> Result<BoxedGate, LoadError>,
+) -> Result<HashMap<usize, usize>, LoadError> {
+    let mut ids = HashMap::new();
+
+    sim.set_net_mode(circuit.nets)
+        .map_err(LoadError::InvalidConnection)?;
+
+    for gate in &circuit.gates {
+        ids.insert(gate.id, sim.add_boxed_gate(create(gate)?));
+    }
+
+    for ConnectionEntry { output, input } in &circuit.connections {
+        let id = |file_id| {
+            ids.get(&file_id)
+                .copied()
+                .ok_or(LoadError::UnknownGate(file_id))
+        };
+        sim.add_connection(id(output.0)?, output.1, id(input.0)?, input.1)
+            .map_err(LoadError::InvalidConnection)?;
+    }
+
+    Ok(ids)
+}
+
+/// Creates a gate of any kind in the registry, or a custom component from
+/// `library`. The `delay` parameter overrides the delay of any kind.
+pub fn create(
+    kind: &str,
+    params: &Params,
+    registry: &GateRegistry,
+    library: &[ComponentDef],
+) -> Result<BoxedGate, LoadError> {
+    let invalid_params = |err: ParamError| LoadError::InvalidParams {
+        kind: kind.to_owned(),
+        message: err.to_string(),
+    };
+
+    let boxed = if kind == KIND {
+        let name = params.get("name").unwrap_or_default();
+        // components can only use components declared before them, which
+        // rules out recursive components
+        let index = library
+            .iter()
+            .position(|component| component.name == name)
+            .ok_or_else(|| LoadError::UnknownKind(format!("{KIND} '{name}'")))?;
+        Subcircuit::boxed(&library[index], registry, &library[..index])?
+    } else {
+        registry
+            .create(kind, params)
+            .ok_or_else(|| LoadError::UnknownKind(kind.to_owned()))?
+            .map_err(invalid_params)?
+    };
+
+    let delay = params
+        .get_or("delay", boxed.delay())
+        .map_err(invalid_params)?;
+    Ok(boxed.with_delay(delay))
+}
+
+/// A custom component backed by its own nested simulation, which advances by
+/// one tick with every update of the component.
+struct Subcircuit {
+    name: String,
+    sim: LogicSimulation,
+    /// Inner gates acting as pins of the component, with widths of the pins.
+    inputs: Vec<(usize, usize)>,
+    outputs: Vec<(usize, usize)>,
+}
+
+impl Subcircuit {
+    fn boxed(
+        component: &ComponentDef,
+        registry: &GateRegistry,
+        library: &[ComponentDef],
+    ) -> Result<BoxedGate, LoadError> {
+        let subcircuit = Subcircuit::new(component, registry, library)?;
+        let widths = |pins: &[(usize, usize)]| pins.iter().map(|(_, width)| *width).collect();
+        Ok(BoxedGate::component(
+            widths(&subcircuit.inputs),
+            widths(&subcircuit.outputs),
+            component.name.clone(),
+            KIND,
+            Box::new(subcircuit),
+        ))
+    }
+
+    fn new(
+        component: &ComponentDef,
+        registry: &GateRegistry,
+        library: &[ComponentDef],
+    ) -> Result<Subcircuit, LoadError> {
+        let mut sim = LogicSimulation::new();
+        // switches and buttons acting as inputs are replaced by pins which
+        // keep the value the component drives them with
+        let ids = instantiate_with(&component.circuit, &mut sim, |gate| {
+            if component.inputs.contains(&gate.id) && INPUT_KINDS.contains(&gate.kind.as_str()) {
+                Ok(BoxedGate::component(
+                    Vec::new(),
+                    vec![1],
+                    gate.kind.clone(),
+                    KIND,
+                    Box::new(InputPin),
+                ))
+            } else {
+                create(&gate.kind, &gate.params, registry, library)
+            }
+        })?;
+        let id = |file_id| {
+            ids.get(&file_id)
+                .copied()
+                .ok_or(LoadError::UnknownGate(file_id))
+        };
+
+        // the first output of an input gate, or the first input of an output
+        // gate, is the pin of the component and decides its width
+        let pin = |file_id, is_input| {
+            let gate_id = id(file_id)?;
+            let (input_widths, output_widths) = sim.get_pin_widths(gate_id).unwrap();
+            match if is_input {
+                output_widths
+            } else {
+                input_widths
+            }
+            .first()
+            {
+                Some(width) => Ok((gate_id, *width)),
+                None => Err(LoadError::InvalidComponentPin {
+                    component: component.name.clone(),
+                    gate: file_id,
+                }),
+            }
+        };
+
+        let (inputs, input_widths): (Vec<_>, Vec<_>) = component
+            .inputs
+            .iter()
+            .map(|input| pin(*input, true))
+            .collect::<Result<Vec<_>, _>>()?
+            .into_iter()
+            .unzip();
+        let (outputs, output_widths): (Vec<_>, Vec<_>) = component
+            .outputs
+            .iter()
+            .map(|output| pin(*output, false))
+            .collect::<Result<Vec<_>, _>>()?
+            .into_iter()
+            .unzip();
+
+        Ok(Subcircuit {
+            name: component.name.clone(),
+            inputs: inputs.into_iter().zip(input_widths).collect(),
+            outputs: outputs.into_iter().zip(output_widths).collect(),
+            sim,
+        })
+    }
+}
+
+/// Input of a nested simulation, its output is set by the component with
+/// [`LogicSimulation::set_output`]. Having no state of its own, it is never
+/// updated and keeps the value until the next one is set.
+struct InputPin;
+
+impl Component for InputPin {
+    fn update(&mut self, _inputs: &[Logic], _outputs: &mut [Logic]) {}
+
+    fn params(&self) -> Params {
+        Params::default()
+    }
+
+    fn is_stateful(&self) -> bool {
+        false
+    }
+}
+
+impl Component for Subcircuit {
+    fn update(&mut self, mut inputs: &[Logic], mut outputs: &mut [Logic]) {
+        for (id, width) in &self.inputs {
+            let (value, rest) = inputs.split_at(*width);
+            // pins are checked to exist when the component is made
+            self.sim.set_output(*id, 0, value).unwrap();
+            inputs = rest;
+        }
+
+        self.sim.simulate();
+
+        for (id, width) in &self.outputs {
+            let (value, rest) = std::mem::take(&mut outputs).split_at_mut(*width);
+            value.copy_from_slice(self.sim.get_input(*id, 0).unwrap());
+            outputs = rest;
+        }
+    }
+
+    fn params(&self) -> Params {
+        Params::default().with("name", &self.name)
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::gates::Switch;
+
+    const Y: bool = true;
+    const N: bool = false;
+
+    /// Switch and button adding up into two leds, shared with the tests of
+    /// the runner and the truth table.
+    pub(crate) const HALF_ADDER: &str = "\
+logic-sim 3
+gate 0 switch 0 0
+gate 1 button 0 100
+gate 2 xor 100 0
+gate 3 and 100 100
+gate 4 led 200 0
+gate 5 led 200 100
+connection 0 0 0 0 2 0 0 0
+connection 1 0 0 0 2 1 0 0
+connection 0 0 0 0 3 0 0 0
+connection 1 0 0 0 3 1 0 0
+connection 2 0 0 0 4 0 0 0
+connection 3 0 0 0 5 0 0 0
+";
+
+    fn half_adder() -> ComponentDef {
+        let circuit = Circuit::parse(HALF_ADDER).unwrap();
+        super::define("half_adder", circuit, &[]).unwrap()
+    }
+
+    #[test]
+    fn subcircuit() {
+        let library = [half_adder()];
+        let params = Params::default().with("name", "half_adder");
+
+        for (inputs, expected_outputs) in [
+            ([N, N], [N, N]),
+            ([N, Y], [Y, N]),
+            ([Y, N], [Y, N]),
+            ([Y, Y], [N, Y]),
+        ] {
+            let mut sim = LogicSimulation::new();
+            let a = sim.add_source(Switch::new(inputs[0]));
+            let b = sim.add_source(Switch::new(inputs[1]));
+            let id = sim
+                .add_boxed_gate(create(KIND, &params, &GateRegistry::default(), &library).unwrap());
+            sim.add_connection(a, 0, id, 0).unwrap();
+            sim.add_connection(b, 0, id, 1).unwrap();
+
+            for _ in 0..4 {
+                sim.simulate();
+            }
+            let outputs = [0, 1].map(|output| sim.get_output(id, output).unwrap()[0].to_bool());
+
+            assert_eq!(outputs, expected_outputs.map(Some));
+            assert_eq!(sim.get_gate_name(id), Ok("half_adder"));
+        }
+    }
+
+    #[test]
+    fn inputs_keep_value() {
+        // the inner switch is on, the component drives it low
+        let mut component = half_adder();
+        component.circuit.gates[0].params.set("on", true);
+        let mut subcircuit = Subcircuit::new(&component, &GateRegistry::default(), &[]).unwrap();
+        let (pin, _) = subcircuit.inputs[0];
+
+        let mut outputs = [Logic::Z; 2];
+        for _ in 0..8 {
+            subcircuit.update(&[Logic::Zero; 2], &mut outputs);
+            assert_eq!(subcircuit.sim.get_output(pin, 0), Ok(&[Logic::Zero][..]));
+        }
+        assert_eq!(outputs, [Logic::Zero; 2]);
+    }
+
+    #[test]
+    fn define() {
+        let mut circuit = half_adder().circuit;
+        // swap positions of the leds, outputs follow them
+        circuit.gates[4].pos.1 = 100.;
+        circuit.gates[5].pos.1 = 0.;
+
+        let component = super::define("adder", circuit.clone(), &[]).unwrap();
+        assert_eq!(component.inputs, [0, 1]);
+        assert_eq!(component.outputs, [5, 4]);
+
+        let library = [component];
+        assert_eq!(
+            super::define("adder", circuit.clone(), &library),
+            Err(ComponentError::DuplicateName("adder".to_owned()))
+        );
+        assert_eq!(
+            super::define("half adder", circuit.clone(), &[]),
+            Err(ComponentError::InvalidName("half adder".to_owned()))
+        );
+
+        circuit.gates.retain(|gate| gate.kind != "led");
+        assert_eq!(
+            super::define("adder", circuit, &[]),
+            Err(ComponentError::NoOutputs)
+        );
+    }
+
+    #[test]
+    fn invalid_pins() {
+        let mut component = half_adder();
+        // the and gate has an output, but leds do not
+        component.inputs[1] = 5;
+        let params = Params::default().with("name", "half_adder");
+
+        assert_eq!(
+            create(KIND, &params, &GateRegistry::default(), &[component]).err(),
+            Some(LoadError::InvalidComponentPin {
+                component: "half_adder".to_owned(),
+                gate: 5
+            })
+        );
+    }
+
+    #[test]
+    fn unknown_component() {
+        let params = Params::default().with("name", "half_adder");
+        assert!(matches!(
+            create(KIND, &params, &GateRegistry::default(), &[]),
+            Err(LoadError::UnknownKind(_))
+        ));
+    }
+
+    #[test]
+    fn delay() {
+        let params = Params::default().with("delay", 4);
+        let mut sim = LogicSimulation::new();
+        let id = sim.add_boxed_gate(create("and", &params, &GateRegistry::default(), &[]).unwrap());
+        assert_eq!(sim.get_gate_delay(id), Ok(4));
+
+        let params = params.with("delay", "slow");
+        assert!(matches!(
+            create("and", &params, &GateRegistry::default(), &[]),
+            Err(LoadError::InvalidParams { .. })
+        ));
+    }
+}
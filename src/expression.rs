@@ -0,0 +1,425 @@
+//! Boolean expressions over named variables, written like `(a & !b) | c`.
+//!
+//! From the lowest precedence to the highest the operators are `|` (or `+`),
+//! `^`, `&` (or `*`) and the prefix `!` (or `~`). Variables start with a
+//! letter followed by letters, digits or `_`, and `0` and `1` are constants.
+
+use std::{collections::BTreeSet, fmt, str::FromStr};
+
+use crate::{logic::Logic, truth_table::MAX_INPUTS};
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Expr {
+    Const(bool),
+    Var(String),
+    Not(Box<Expr>),
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+    Xor(Box<Expr>, Box<Expr>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ExprError {
+    /// The expression can not be parsed, columns are counted in characters
+    /// from 1.
+    Syntax { column: usize, message: String },
+    /// Only expressions of up to [`MAX_INPUTS`] variables can be simplified.
+    TooManyVariables(usize),
+}
+
+impl fmt::Display for ExprError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ExprError::Syntax { column, message } => write!(f, "column {column}: {message}"),
+            ExprError::TooManyVariables(vars) => {
+                write!(f, "{vars} variables, expressions have at most {MAX_INPUTS}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for ExprError {}
+
+impl Expr {
+    /// Names of all variables, sorted and without duplicates.
+    pub fn vars(&self) -> Vec<String> {
+        fn collect<'a>(expr: &'a Expr, vars: &mut BTreeSet<&'a str>) {
+            match expr {
+                Expr::Const(_) => {}
+                Expr::Var(name) => {
+                    vars.insert(name);
+                }
+                Expr::Not(a) => collect(a, vars),
+                Expr::And(a, b) | Expr::Or(a, b) | Expr::Xor(a, b) => {
+                    collect(a, vars);
+                    collect(b, vars);
+                }
+            }
+        }
+
+        let mut vars = BTreeSet::new();
+        collect(self, &mut vars);
+        vars.into_iter().map(str::to_owned).collect()
+    }
+
+    /// Evaluates the expression with the values of the variables given by
+    /// `value`.
+    pub fn eval(&self, value: &impl Fn(&str) -> bool) -> bool {
+        match self {
+            Expr::Const(constant) => *constant,
+            Expr::Var(name) => value(name),
+            Expr::Not(a) => !a.eval(value),
+            Expr::And(a, b) => a.eval(value) && b.eval(value),
+            Expr::Or(a, b) => a.eval(value) || b.eval(value),
+            Expr::Xor(a, b) => a.eval(value) != b.eval(value),
+        }
+    }
+
+    /// Values of the expression for every combination of `vars`, ordered
+    /// like the rows of a [`crate::truth_table::TruthTable`].
+    pub fn column(&self, vars: &[String]) -> Vec<Logic> {
+        (0..1usize << vars.len())
+            .map(|row| {
+                let value = |name: &str| {
+                    let bit = vars.iter().position(|var| var == name).unwrap();
+                    row >> (vars.len() - 1 - bit) & 1 == 1
+                };
+                self.eval(&value).into()
+            })
+            .collect()
+    }
+
+    /// The same function as a minimal sum of products, see [`sum_of_products`].
+    pub fn simplify(&self) -> Result<Expr, ExprError> {
+        let vars = self.vars();
+        if vars.len() > MAX_INPUTS {
+            return Err(ExprError::TooManyVariables(vars.len()));
+        }
+        Ok(sum_of_products(&vars, &self.column(&vars)))
+    }
+
+    fn precedence(&self) -> u8 {
+        match self {
+            Expr::Or(..) => 0,
+            Expr::Xor(..) => 1,
+            Expr::And(..) => 2,
+            Expr::Not(_) => 3,
+            Expr::Const(_) | Expr::Var(_) => 4,
+        }
+    }
+}
+
+/// Builds a minimal sum of products with the Quine-McCluskey method. `column`
+/// holds the value of the function for every combination of `vars`, ordered
+/// like the rows of a [`crate::truth_table::TruthTable`], rows which are
+/// neither `0` nor `1` can take either value.
+///
+/// # Panics
+///
+/// When `column` does not have a row for every combination of `vars`.
+pub fn sum_of_products(vars: &[String], column: &[Logic]) -> Expr {
+    assert_eq!(column.len(), 1 << vars.len(), "one row per combination");
+
+    let minterms: Vec<usize> = (0..column.len())
+        .filter(|row| column[*row] == Logic::One)
+        .collect();
+    let dont_cares = (0..column.len()).filter(|row| column[*row].to_bool().is_none());
+
+    // implicants are rows with the bits of `mask` left open
+    let mut implicants: BTreeSet<(usize, usize)> = minterms
+        .iter()
+        .copied()
+        .chain(dont_cares)
+        .map(|row| (row, 0))
+        .collect();
+    let mut primes = BTreeSet::new();
+    while !implicants.is_empty() {
+        let mut merged = BTreeSet::new();
+        let mut combined = BTreeSet::new();
+        for (a, mask) in &implicants {
+            for (b, other_mask) in implicants.range((a + 1, 0)..) {
+                let diff = a ^ b;
+                if mask == other_mask && diff.count_ones() == 1 {
+                    merged.insert((a & b, mask | diff));
+                    combined.insert((*a, *mask));
+                    combined.insert((*b, *other_mask));
+                }
+            }
+        }
+        primes.extend(implicants.difference(&combined).copied());
+        implicants = merged;
+    }
+
+    let covers = |(value, mask): (usize, usize), row: usize| row & !mask == value;
+
+    // essential primes first, then whichever covers the most rows left
+    let mut chosen: Vec<(usize, usize)> = Vec::new();
+    let mut uncovered = minterms.clone();
+    for row in &minterms {
+        let mut covering = primes.iter().filter(|prime| covers(**prime, *row));
+        if let (Some(prime), None) = (covering.next(), covering.next()) {
+            if !chosen.contains(prime) {
+                chosen.push(*prime);
+            }
+        }
+    }
+    uncovered.retain(|row| !chosen.iter().any(|prime| covers(*prime, *row)));
+    while !uncovered.is_empty() {
+        let best = *primes
+            .iter()
+            .max_by_key(|prime| {
+                let count = uncovered
+                    .iter()
+                    .filter(|row| covers(**prime, **row))
+                    .count();
+                // prefer fewer literals, then the lowest rows
+                (count, prime.1.count_ones(), std::cmp::Reverse(**prime))
+            })
+            .unwrap();
+        uncovered.retain(|row| !covers(best, *row));
+        chosen.push(best);
+    }
+    chosen.sort();
+
+    let product = |(value, mask): (usize, usize)| {
+        (0..vars.len())
+            .filter_map(|index| {
+                let bit = 1 << (vars.len() - 1 - index);
+                let var = Expr::Var(vars[index].clone());
+                match (mask & bit == 0, value & bit != 0) {
+                    (false, _) => None,
+                    (true, true) => Some(var),
+                    (true, false) => Some(Expr::Not(Box::new(var))),
+                }
+            })
+            .reduce(|a, b| Expr::And(Box::new(a), Box::new(b)))
+            .unwrap_or(Expr::Const(true))
+    };
+
+    chosen
+        .into_iter()
+        .map(product)
+        .reduce(|a, b| Expr::Or(Box::new(a), Box::new(b)))
+        .unwrap_or(Expr::Const(false))
+}
+
+impl fmt::Display for Expr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // operators are left associative, so equal operators on the right
+        // need parentheses as well
+        let operand = |f: &mut fmt::Formatter<'_>, operand: &Expr, right: bool| {
+            let precedence = operand.precedence();
+            if precedence < self.precedence() || (right && precedence == self.precedence()) {
+                write!(f, "({operand})")
+            } else {
+                write!(f, "{operand}")
+            }
+        };
+
+        let (a, operator, b) = match self {
+            Expr::Const(constant) => return write!(f, "{}", *constant as u8),
+            Expr::Var(name) => return f.write_str(name),
+            Expr::Not(a) => {
+                f.write_str("!")?;
+                return operand(f, a, false);
+            }
+            Expr::And(a, b) => (a, "&", b),
+            Expr::Or(a, b) => (a, "|", b),
+            Expr::Xor(a, b) => (a, "^", b),
+        };
+        operand(f, a, false)?;
+        write!(f, " {operator} ")?;
+        operand(f, b, true)
+    }
+}
+
+impl FromStr for Expr {
+    type Err = ExprError;
+
+    fn from_str(source: &str) -> Result<Expr, ExprError> {
+        let mut parser = Parser {
+            chars: source.chars().collect(),
+            pos: 0,
+        };
+        let expr = parser.or()?;
+        match parser.peek() {
+            None => Ok(expr),
+            Some(c) => Err(parser.error(format!("unexpected '{c}'"))),
+        }
+    }
+}
+
+struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+}
+
+impl Parser {
+    /// The next character which is not whitespace.
+    fn peek(&mut self) -> Option<char> {
+        while self.chars.get(self.pos).is_some_and(|c| c.is_whitespace()) {
+            self.pos += 1;
+        }
+        self.chars.get(self.pos).copied()
+    }
+
+    fn eat(&mut self, operators: &[char]) -> bool {
+        let found = self.peek().is_some_and(|c| operators.contains(&c));
+        if found {
+            self.pos += 1;
+        }
+        found
+    }
+
+    fn error(&self, message: String) -> ExprError {
+        ExprError::Syntax {
+            column: self.pos + 1,
+            message,
+        }
+    }
+
+    fn binary(
+        &mut self,
+        operators: &[char],
+        operand: fn(&mut Parser) -> Result<Expr, ExprError>,
+        make: fn(Box<Expr>, Box<Expr>) -> Expr,
+    ) -> Result<Expr, ExprError> {
+        let mut expr = operand(self)?;
+        while self.eat(operators) {
+            expr = make(Box::new(expr), Box::new(operand(self)?));
+        }
+        Ok(expr)
+    }
+
+    fn or(&mut self) -> Result<Expr, ExprError> {
+        self.binary(&['|', '+'], Parser::xor, Expr::Or)
+    }
+
+    fn xor(&mut self) -> Result<Expr, ExprError> {
+        self.binary(&['^'], Parser::and, Expr::Xor)
+    }
+
+    fn and(&mut self) -> Result<Expr, ExprError> {
+        self.binary(&['&', '*'], Parser::not, Expr::And)
+    }
+
+    fn not(&mut self) -> Result<Expr, ExprError> {
+        if self.eat(&['!', '~']) {
+            return Ok(Expr::Not(Box::new(self.not()?)));
+        }
+
+        match self.peek() {
+            Some('(') => {
+                self.pos += 1;
+                let expr = self.or()?;
+                if !self.eat(&[')']) {
+                    return Err(self.error("expected ')'".to_owned()));
+                }
+                Ok(expr)
+            }
+            Some('0') => {
+                self.pos += 1;
+                Ok(Expr::Const(false))
+            }
+            Some('1') => {
+                self.pos += 1;
+                Ok(Expr::Const(true))
+            }
+            Some(c) if c.is_alphabetic() => {
+                let start = self.pos;
+                while self
+                    .chars
+                    .get(self.pos)
+                    .is_some_and(|c| c.is_alphanumeric() || *c == '_')
+                {
+                    self.pos += 1;
+                }
+                Ok(Expr::Var(self.chars[start..self.pos].iter().collect()))
+            }
+            Some(c) => Err(self.error(format!("unexpected '{c}'"))),
+            None => Err(self.error("unexpected end of expression".to_owned())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const Y: Logic = Logic::One;
+    const N: Logic = Logic::Zero;
+    const X: Logic = Logic::X;
+
+    fn parse(source: &str) -> Expr {
+        source.parse().unwrap()
+    }
+
+    fn vars(names: &str) -> Vec<String> {
+        names.split_whitespace().map(str::to_owned).collect()
+    }
+
+    #[test]
+    fn parse_and_print() {
+        for (source, printed) in [
+            ("(A & !B) | C", "A & !B | C"),
+            ("a | b & c", "a | b & c"),
+            ("(a | b) & c", "(a | b) & c"),
+            ("a ^ (b ^ c)", "a ^ (b ^ c)"),
+            ("~(a + b) * 1", "!(a | b) & 1"),
+            ("!!in_1", "!!in_1"),
+        ] {
+            let expr = parse(source);
+            assert_eq!(expr.to_string(), printed);
+            assert_eq!(parse(printed), expr);
+        }
+
+        for (source, column) in [("a &", 4), ("(a | b", 7), ("a $ b", 3), ("a b", 3)] {
+            assert!(matches!(
+                source.parse::<Expr>(),
+                Err(ExprError::Syntax { column: c, .. }) if c == column
+            ));
+        }
+    }
+
+    #[test]
+    fn eval() {
+        let expr = parse("(A & !B) | C");
+        assert_eq!(expr.vars(), ["A", "B", "C"]);
+        #[rustfmt::skip]
+        assert_eq!(expr.column(&expr.vars()), [N, Y, N, Y, Y, Y, N, Y]);
+    }
+
+    #[test]
+    fn sum_of_products() {
+        for (names, column, expected) in [
+            ("a b", vec![N, Y, Y, N], "!a & b | a & !b"),
+            ("a b c", vec![N, N, N, N, Y, Y, N, Y], "a & !b | a & c"),
+            // don't cares let the product grow
+            ("a b", vec![N, X, Y, Y], "a"),
+            ("a b", vec![Y, X, Y, Y], "1"),
+            ("a b", vec![N, N, X, N], "0"),
+        ] {
+            let expr = super::sum_of_products(&vars(names), &column);
+            assert_eq!(expr.to_string(), expected);
+        }
+    }
+
+    #[test]
+    fn simplify() {
+        let expr = parse("(a & b) | (a & !b) | (a & b & c)");
+        assert_eq!(expr.simplify().unwrap().to_string(), "a");
+
+        let expr = parse("a ^ b ^ c");
+        let simplified = expr.simplify().unwrap();
+        assert_eq!(simplified.column(&expr.vars()), expr.column(&expr.vars()));
+
+        let many = (0..=MAX_INPUTS)
+            .map(|var| format!("v{var}"))
+            .collect::<Vec<_>>()
+            .join(" & ");
+        assert_eq!(
+            parse(&many).simplify(),
+            Err(ExprError::TooManyVariables(MAX_INPUTS + 1))
+        );
+    }
+}
@@ -0,0 +1,306 @@
+//! Boolean expressions of signals in a simulation, found by following the
+//! connections backwards from a pin to the sources driving it.
+
+use std::{collections::HashMap, fmt};
+
+use crate::{
+    expression::Expr,
+    gates::{
+        And, Button, Clock, Constant, Gate, Nand, Nor, Not, Or, Source, Switch, Xnor, Xor, Yes,
+    },
+    logic_simulation::{LogicSimulation, NetMode, SimError},
+};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ExtractError {
+    Sim(SimError),
+    /// The signal feeds back into itself through the gates, listed in the
+    /// order the signal passes them.
+    FeedbackLoop(Vec<usize>),
+    /// The gate has no boolean equivalent, like buses, tri-state buffers and
+    /// custom components.
+    UnsupportedGate(usize),
+    Undriven {
+        gate: usize,
+        input: usize,
+    },
+    /// Several outputs drive the input and [`NetMode::Contention`] does not
+    /// combine them.
+    MultipleDrivers {
+        gate: usize,
+        input: usize,
+    },
+}
+
+impl fmt::Display for ExtractError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ExtractError::Sim(err) => write!(f, "{err}"),
+            ExtractError::FeedbackLoop(gates) => {
+                let gates: Vec<_> = gates.iter().map(usize::to_string).collect();
+                write!(f, "feedback loop through gates {}", gates.join(", "))
+            }
+            ExtractError::UnsupportedGate(gate) => {
+                write!(f, "gate {gate} has no boolean expression")
+            }
+            ExtractError::Undriven { gate, input } => {
+                write!(f, "input {input} of gate {gate} is not connected")
+            }
+            ExtractError::MultipleDrivers { gate, input } => {
+                write!(f, "input {input} of gate {gate} has several drivers")
+            }
+        }
+    }
+}
+
+impl std::error::Error for ExtractError {}
+
+impl From<SimError> for ExtractError {
+    fn from(err: SimError) -> ExtractError {
+        ExtractError::Sim(err)
+    }
+}
+
+/// Expression of an output of a gate over the sources driving it, the
+/// variable of each source is named by `name`. Several drivers of one input
+/// are combined according to the [`NetMode`] of the simulation.
+pub fn extract(
+    sim: &LogicSimulation,
+    gate: usize,
+    output: usize,
+    name: &impl Fn(usize) -> String,
+) -> Result<Expr, ExtractError> {
+    Extractor::new(sim, name).output(gate, output)
+}
+
+/// Expression of the signal arriving at an input of a gate, see [`extract`].
+pub fn extract_input(
+    sim: &LogicSimulation,
+    gate: usize,
+    input: usize,
+    name: &impl Fn(usize) -> String,
+) -> Result<Expr, ExtractError> {
+    Extractor::new(sim, name).input(gate, input)
+}
+
+struct Extractor<'a, F> {
+    sim: &'a LogicSimulation,
+    name: &'a F,
+    /// Gates between the pin the expression is extracted for and the gate
+    /// being visited.
+    path: Vec<usize>,
+    /// Expressions of the outputs visited so far, so a signal fanning out to
+    /// several gates is followed back only once.
+    outputs: HashMap<(usize, usize), Expr>,
+}
+
+impl<'a, F: Fn(usize) -> String> Extractor<'a, F> {
+    fn new(sim: &'a LogicSimulation, name: &'a F) -> Extractor<'a, F> {
+        Extractor {
+            sim,
+            name,
+            path: Vec::new(),
+            outputs: HashMap::new(),
+        }
+    }
+
+    fn output(&mut self, gate: usize, output: usize) -> Result<Expr, ExtractError> {
+        if let Some(expr) = self.outputs.get(&(gate, output)) {
+            return Ok(expr.clone());
+        }
+        let expr = self.visit(gate, output)?;
+        self.outputs.insert((gate, output), expr.clone());
+        Ok(expr)
+    }
+
+    fn visit(&mut self, gate: usize, output: usize) -> Result<Expr, ExtractError> {
+        let (inputs, outputs) = self.sim.get_pin_widths(gate)?;
+        if outputs.get(output).is_some_and(|width| *width != 1)
+            || inputs.iter().any(|width| *width != 1)
+        {
+            return Err(ExtractError::UnsupportedGate(gate));
+        }
+
+        let kind = self.sim.get_gate_kind(gate)?;
+        if [Switch::KIND, Button::KIND, Clock::KIND].contains(&kind) {
+            return Ok(Expr::Var((self.name)(gate)));
+        }
+        if kind == Constant::KIND {
+            let value = self.sim.get_gate_params(gate)?.get("value") == Some("true");
+            return Ok(Expr::Const(value));
+        }
+
+        if let Some(start) = self.path.iter().position(|id| *id == gate) {
+            // the path runs against the signal
+            let mut cycle = self.path[start..].to_vec();
+            cycle.reverse();
+            return Err(ExtractError::FeedbackLoop(cycle));
+        }
+
+        self.path.push(gate);
+        let operands = (0..inputs.len())
+            .map(|input| self.input(gate, input))
+            .collect::<Result<Vec<_>, _>>()?;
+        self.path.pop();
+
+        // gates with more inputs chain their operation, like ((a & b) & c)
+        let chain = |operation: fn(Box<Expr>, Box<Expr>) -> Expr| {
+            operands
+                .iter()
+                .cloned()
+                .reduce(|a, b| operation(Box::new(a), Box::new(b)))
+                .ok_or(ExtractError::UnsupportedGate(gate))
+        };
+        let operand = || {
+            operands
+                .first()
+                .cloned()
+                .ok_or(ExtractError::UnsupportedGate(gate))
+        };
+        let not = |expr| Expr::Not(Box::new(expr));
+        Ok(match kind {
+            Yes::KIND => operand()?,
+            Not::KIND => not(operand()?),
+            And::KIND => chain(Expr::And)?,
+            Or::KIND => chain(Expr::Or)?,
+            Xor::KIND => chain(Expr::Xor)?,
+            Nand::KIND => not(chain(Expr::And)?),
+            Nor::KIND => not(chain(Expr::Or)?),
+            Xnor::KIND => not(chain(Expr::Xor)?),
+            _ => return Err(ExtractError::UnsupportedGate(gate)),
+        })
+    }
+
+    fn input(&mut self, gate: usize, input: usize) -> Result<Expr, ExtractError> {
+        let drivers = self.sim.get_drivers(gate, input)?;
+        let combine = match self.sim.net_mode() {
+            NetMode::WiredAnd => Expr::And,
+            // exclusive nets never have several drivers
+            NetMode::WiredOr | NetMode::Exclusive => Expr::Or,
+            NetMode::Contention if drivers.len() > 1 => {
+                return Err(ExtractError::MultipleDrivers { gate, input })
+            }
+            NetMode::Contention => Expr::Or,
+        };
+
+        drivers
+            .iter()
+            .map(|(driver, output)| self.output(*driver, *output))
+            .reduce(|a, b| Ok(combine(Box::new(a?), Box::new(b?))))
+            .unwrap_or(Err(ExtractError::Undriven { gate, input }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gates::{Led, Wide};
+
+    fn name(id: usize) -> String {
+        char::from(b'a' + id as u8).to_string()
+    }
+
+    #[test]
+    fn nand_xor() {
+        let mut sim = LogicSimulation::new();
+        let a = sim.add_source(Switch::default());
+        let b = sim.add_source(Switch::default());
+        let nand = sim.add_gate(Nand);
+        let nand_a = sim.add_gate(Nand);
+        let nand_b = sim.add_gate(Nand);
+        let xor = sim.add_gate(Nand);
+        let led = sim.add_gate(Led);
+        for (from, to, input) in [
+            (a, nand, 0),
+            (b, nand, 1),
+            (a, nand_a, 0),
+            (nand, nand_a, 1),
+            (nand, nand_b, 0),
+            (b, nand_b, 1),
+            (nand_a, xor, 0),
+            (nand_b, xor, 1),
+            (xor, led, 0),
+        ] {
+            sim.add_connection(from, 0, to, input).unwrap();
+        }
+
+        let expr = extract_input(&sim, led, 0, &name).unwrap();
+        assert_eq!(expr, extract(&sim, xor, 0, &name).unwrap());
+        assert_eq!(expr.to_string(), "!(!(a & !(a & b)) & !(!(a & b) & b))");
+        assert_eq!(expr.simplify().unwrap().to_string(), "!a & b | a & !b");
+    }
+
+    #[test]
+    fn wide() {
+        let mut sim = LogicSimulation::new();
+        let nor = sim.add_dynamic_gate(Wide::new(Nor, 3));
+        for input in 0..3 {
+            let switch = sim.add_source(Switch::default());
+            sim.add_connection(switch, 0, nor, input).unwrap();
+        }
+
+        let expr = extract(&sim, nor, 0, &name).unwrap();
+        assert_eq!(expr.to_string(), "!(b | c | d)");
+    }
+
+    #[test]
+    fn constant() {
+        let mut sim = LogicSimulation::new();
+        let a = sim.add_source(Switch::default());
+        let one = sim.add_gate(Constant::new(true));
+        let and = sim.add_gate(And);
+        sim.add_connection(a, 0, and, 0).unwrap();
+        sim.add_connection(one, 0, and, 1).unwrap();
+
+        let expr = extract(&sim, and, 0, &name).unwrap();
+        assert_eq!(expr.to_string(), "a & 1");
+    }
+
+    #[test]
+    fn errors() {
+        // cross coupled nor gates of a latch
+        let mut sim = LogicSimulation::new();
+        let set = sim.add_source(Switch::default());
+        let reset = sim.add_source(Switch::default());
+        let q = sim.add_gate(Nor);
+        let not_q = sim.add_gate(Nor);
+        let not = sim.add_gate(Not);
+        sim.add_connection(reset, 0, q, 0).unwrap();
+        sim.add_connection(not_q, 0, q, 1).unwrap();
+        sim.add_connection(set, 0, not_q, 0).unwrap();
+        sim.add_connection(q, 0, not_q, 1).unwrap();
+
+        assert_eq!(
+            extract(&sim, q, 0, &name),
+            Err(ExtractError::FeedbackLoop(vec![not_q, q]))
+        );
+        assert_eq!(
+            extract(&sim, not, 0, &name),
+            Err(ExtractError::Undriven {
+                gate: not,
+                input: 0
+            })
+        );
+
+        // several drivers follow the net mode
+        sim.add_connection(set, 0, not, 0).unwrap();
+        sim.add_connection(reset, 0, not, 0).unwrap();
+        assert_eq!(
+            extract(&sim, not, 0, &name).unwrap().to_string(),
+            "!(a | b)"
+        );
+        sim.set_net_mode(NetMode::WiredAnd).unwrap();
+        assert_eq!(
+            extract(&sim, not, 0, &name).unwrap().to_string(),
+            "!(a & b)"
+        );
+        sim.set_net_mode(NetMode::Contention).unwrap();
+        assert_eq!(
+            extract(&sim, not, 0, &name),
+            Err(ExtractError::MultipleDrivers {
+                gate: not,
+                input: 0
+            })
+        );
+    }
+}
@@ -0,0 +1,477 @@
+//! Single bit storage: SR and D latches, and D, JK and T flip-flops.
+//!
+//! Latches follow their inputs while they are enabled, flip-flops change only
+//! on an edge of their last input, the clock, which is the rising edge unless
+//! they are made with [`Edge::Falling`]. All of them start cleared to zero and
+//! output the stored bit and its complement. Like the controls of registers, a
+//! floating input reads as its default, while an unknown input that could
+//! change the state makes it unknown.
+
+use std::{fmt, str::FromStr};
+
+use crate::{
+    gates::{DynamicGate, Params},
+    logic::Logic,
+    registers::control,
+};
+
+/// Clock edge a flip-flop changes on.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Edge {
+    /// Low to high.
+    #[default]
+    Rising,
+    /// High to low.
+    Falling,
+}
+
+impl Edge {
+    pub const ALL: [Edge; 2] = [Edge::Rising, Edge::Falling];
+
+    /// Whether the clock went through the edge since the last update, `last`
+    /// keeps the clock of the last update.
+    pub(crate) fn detect(self, last: &mut Logic, clock: Logic) -> bool {
+        let (from, to) = match self {
+            Edge::Rising => (Logic::Zero, Logic::One),
+            Edge::Falling => (Logic::One, Logic::Zero),
+        };
+        let edge = *last == from && clock == to;
+        *last = clock;
+        edge
+    }
+
+    /// Stable name used in circuit files.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Edge::Rising => "rising",
+            Edge::Falling => "falling",
+        }
+    }
+}
+
+impl fmt::Display for Edge {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for Edge {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Edge, ()> {
+        Edge::ALL
+            .into_iter()
+            .find(|edge| edge.as_str() == s)
+            .ok_or(())
+    }
+}
+
+/// Next state of a bit which is set, reset or kept, `None` for unknown
+/// inputs. Setting and resetting at once makes it unknown.
+fn set_reset(state: Logic, set: Option<bool>, reset: Option<bool>) -> Logic {
+    match (set, reset) {
+        (Some(false), Some(false)) => state,
+        (Some(true), Some(false)) => Logic::One,
+        (Some(false), Some(true)) => Logic::Zero,
+        _ => Logic::X,
+    }
+}
+
+/// Drives the stored bit and its complement.
+fn output(state: Logic, outputs: &mut [Logic]) {
+    outputs[0] = state;
+    outputs[1] = !state;
+}
+
+/// Sets its bit while `set` is high and clears it while `reset` is high, both
+/// default low.
+pub struct SrLatch {
+    state: Logic,
+}
+
+impl SrLatch {
+    pub const NAME: &'static str = "SR";
+    pub const KIND: &'static str = "sr_latch";
+
+    pub fn new() -> SrLatch {
+        SrLatch { state: Logic::Zero }
+    }
+}
+
+impl Default for SrLatch {
+    fn default() -> SrLatch {
+        SrLatch::new()
+    }
+}
+
+impl DynamicGate for SrLatch {
+    fn name(&self) -> &'static str {
+        Self::NAME
+    }
+
+    fn kind(&self) -> &'static str {
+        Self::KIND
+    }
+
+    fn input_widths(&self) -> Vec<usize> {
+        vec![1, 1]
+    }
+
+    fn output_widths(&self) -> Vec<usize> {
+        vec![1, 1]
+    }
+
+    fn update(&mut self, inputs: &[Logic], outputs: &mut [Logic]) {
+        let set = control(inputs[0], false);
+        let reset = control(inputs[1], false);
+        self.state = set_reset(self.state, set, reset);
+        output(self.state, outputs);
+    }
+
+    fn params(&self) -> Params {
+        Params::default()
+    }
+}
+
+/// Follows its data input while `enable` (default low) is high and keeps the
+/// last value while it is low.
+pub struct DLatch {
+    state: Logic,
+}
+
+impl DLatch {
+    pub const NAME: &'static str = "DLATCH";
+    pub const KIND: &'static str = "d_latch";
+
+    pub fn new() -> DLatch {
+        DLatch { state: Logic::Zero }
+    }
+}
+
+impl Default for DLatch {
+    fn default() -> DLatch {
+        DLatch::new()
+    }
+}
+
+impl DynamicGate for DLatch {
+    fn name(&self) -> &'static str {
+        Self::NAME
+    }
+
+    fn kind(&self) -> &'static str {
+        Self::KIND
+    }
+
+    fn input_widths(&self) -> Vec<usize> {
+        vec![1, 1]
+    }
+
+    fn output_widths(&self) -> Vec<usize> {
+        vec![1, 1]
+    }
+
+    fn update(&mut self, inputs: &[Logic], outputs: &mut [Logic]) {
+        match control(inputs[1], false) {
+            Some(true) => self.state = inputs[0].read(),
+            Some(false) => {}
+            None => self.state = Logic::X,
+        }
+        output(self.state, outputs);
+    }
+
+    fn params(&self) -> Params {
+        Params::default()
+    }
+}
+
+/// Stores its data input on a clock edge.
+pub struct DFlipFlop {
+    edge: Edge,
+    state: Logic,
+    clock: Logic,
+}
+
+impl DFlipFlop {
+    pub const NAME: &'static str = "DFF";
+    pub const KIND: &'static str = "d_flip_flop";
+
+    pub fn new(edge: Edge) -> DFlipFlop {
+        DFlipFlop {
+            edge,
+            state: Logic::Zero,
+            clock: Logic::Z,
+        }
+    }
+}
+
+impl DynamicGate for DFlipFlop {
+    fn name(&self) -> &'static str {
+        Self::NAME
+    }
+
+    fn kind(&self) -> &'static str {
+        Self::KIND
+    }
+
+    fn input_widths(&self) -> Vec<usize> {
+        vec![1, 1]
+    }
+
+    fn output_widths(&self) -> Vec<usize> {
+        vec![1, 1]
+    }
+
+    fn update(&mut self, inputs: &[Logic], outputs: &mut [Logic]) {
+        if self.edge.detect(&mut self.clock, inputs[1]) {
+            self.state = inputs[0].read();
+        }
+        output(self.state, outputs);
+    }
+
+    fn params(&self) -> Params {
+        Params::default().with("edge", self.edge)
+    }
+}
+
+/// Sets its bit on a clock edge while `j` is high, clears it while `k` is high
+/// and toggles it while both are. Both default low, so the bit is kept.
+pub struct JkFlipFlop {
+    edge: Edge,
+    state: Logic,
+    clock: Logic,
+}
+
+impl JkFlipFlop {
+    pub const NAME: &'static str = "JKFF";
+    pub const KIND: &'static str = "jk_flip_flop";
+
+    pub fn new(edge: Edge) -> JkFlipFlop {
+        JkFlipFlop {
+            edge,
+            state: Logic::Zero,
+            clock: Logic::Z,
+        }
+    }
+}
+
+impl DynamicGate for JkFlipFlop {
+    fn name(&self) -> &'static str {
+        Self::NAME
+    }
+
+    fn kind(&self) -> &'static str {
+        Self::KIND
+    }
+
+    fn input_widths(&self) -> Vec<usize> {
+        vec![1, 1, 1]
+    }
+
+    fn output_widths(&self) -> Vec<usize> {
+        vec![1, 1]
+    }
+
+    fn update(&mut self, inputs: &[Logic], outputs: &mut [Logic]) {
+        if self.edge.detect(&mut self.clock, inputs[2]) {
+            self.state = match (control(inputs[0], false), control(inputs[1], false)) {
+                (Some(true), Some(true)) => !self.state,
+                (j, k) => set_reset(self.state, j, k),
+            };
+        }
+        output(self.state, outputs);
+    }
+
+    fn params(&self) -> Params {
+        Params::default().with("edge", self.edge)
+    }
+}
+
+/// Toggles its bit on a clock edge while `t` (default high) is high, so it
+/// halves the frequency of its clock when left unconnected.
+pub struct TFlipFlop {
+    edge: Edge,
+    state: Logic,
+    clock: Logic,
+}
+
+impl TFlipFlop {
+    pub const NAME: &'static str = "TFF";
+    pub const KIND: &'static str = "t_flip_flop";
+
+    pub fn new(edge: Edge) -> TFlipFlop {
+        TFlipFlop {
+            edge,
+            state: Logic::Zero,
+            clock: Logic::Z,
+        }
+    }
+}
+
+impl DynamicGate for TFlipFlop {
+    fn name(&self) -> &'static str {
+        Self::NAME
+    }
+
+    fn kind(&self) -> &'static str {
+        Self::KIND
+    }
+
+    fn input_widths(&self) -> Vec<usize> {
+        vec![1, 1]
+    }
+
+    fn output_widths(&self) -> Vec<usize> {
+        vec![1, 1]
+    }
+
+    fn update(&mut self, inputs: &[Logic], outputs: &mut [Logic]) {
+        if self.edge.detect(&mut self.clock, inputs[1]) {
+            self.state = match control(inputs[0], true) {
+                Some(true) => !self.state,
+                Some(false) => self.state,
+                None => Logic::X,
+            };
+        }
+        output(self.state, outputs);
+    }
+
+    fn params(&self) -> Params {
+        Params::default().with("edge", self.edge)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gates::tests::*;
+
+    /// Applies the inputs of every step in order, checking the outputs after
+    /// each of them.
+    fn test_sequence(gate: &mut impl DynamicGate, steps: &[(&[Logic], [Logic; 2])]) {
+        let mut outputs = [Z; 2];
+        for (step, (inputs, expected)) in steps.iter().enumerate() {
+            gate.update(inputs, &mut outputs);
+            assert_eq!(outputs, *expected, "step {step}");
+        }
+    }
+
+    #[test]
+    fn edges() {
+        let mut last = Z;
+        let rising: Vec<_> = [N, Y, Y, N, X, Y, Z, Y]
+            .into_iter()
+            .map(|clock| Edge::Rising.detect(&mut last, clock))
+            .collect();
+        assert_eq!(
+            rising,
+            [false, true, false, false, false, false, false, false]
+        );
+
+        let mut last = Z;
+        let falling: Vec<_> = [Y, N, N, Y, N]
+            .into_iter()
+            .map(|clock| Edge::Falling.detect(&mut last, clock))
+            .collect();
+        assert_eq!(falling, [false, true, false, false, true]);
+
+        assert_eq!("falling".parse(), Ok(Edge::Falling));
+        assert_eq!(Edge::Rising.to_string(), "rising");
+    }
+
+    #[test]
+    fn sr_latch() {
+        // set, reset
+        test_sequence(
+            &mut SrLatch::new(),
+            &[
+                (&[Z, Z], [N, Y]),
+                (&[Y, N], [Y, N]),
+                (&[N, N], [Y, N]),
+                (&[N, Y], [N, Y]),
+                (&[Z, N], [N, Y]),
+                (&[Y, Y], [X, X]),
+                (&[Y, N], [Y, N]),
+                (&[X, N], [X, X]),
+            ],
+        );
+    }
+
+    #[test]
+    fn d_latch() {
+        // data, enable
+        test_sequence(
+            &mut DLatch::new(),
+            &[
+                (&[Y, Z], [N, Y]),
+                (&[Y, Y], [Y, N]),
+                (&[N, Y], [N, Y]),
+                (&[Y, N], [N, Y]),
+                (&[Z, Y], [X, X]),
+                (&[Y, X], [X, X]),
+            ],
+        );
+    }
+
+    #[test]
+    fn d_flip_flop() {
+        // data, clock
+        test_sequence(
+            &mut DFlipFlop::new(Edge::Rising),
+            &[
+                (&[Y, N], [N, Y]),
+                (&[Y, Y], [Y, N]),
+                (&[N, Y], [Y, N]),
+                (&[N, N], [Y, N]),
+                (&[N, Y], [N, Y]),
+                (&[X, N], [N, Y]),
+                (&[X, Y], [X, X]),
+            ],
+        );
+
+        test_sequence(
+            &mut DFlipFlop::new(Edge::Falling),
+            &[(&[Y, N], [N, Y]), (&[Y, Y], [N, Y]), (&[Y, N], [Y, N])],
+        );
+    }
+
+    #[test]
+    fn jk_flip_flop() {
+        // j, k, clock
+        test_sequence(
+            &mut JkFlipFlop::new(Edge::Rising),
+            &[
+                (&[Y, N, N], [N, Y]),
+                (&[Y, N, Y], [Y, N]),
+                (&[Z, Z, N], [Y, N]),
+                (&[Z, Z, Y], [Y, N]),
+                (&[Y, Y, N], [Y, N]),
+                (&[Y, Y, Y], [N, Y]),
+                (&[Y, Y, N], [N, Y]),
+                (&[Y, Y, Y], [Y, N]),
+                (&[N, Y, N], [Y, N]),
+                (&[N, Y, Y], [N, Y]),
+                (&[X, N, N], [N, Y]),
+                (&[X, N, Y], [X, X]),
+            ],
+        );
+    }
+
+    #[test]
+    fn t_flip_flop() {
+        // t, clock
+        test_sequence(
+            &mut TFlipFlop::new(Edge::Falling),
+            &[
+                (&[Z, Y], [N, Y]),
+                (&[Z, N], [Y, N]),
+                (&[Z, Y], [Y, N]),
+                (&[Z, N], [N, Y]),
+                (&[N, Y], [N, Y]),
+                (&[N, N], [N, Y]),
+                (&[X, Y], [N, Y]),
+                (&[X, N], [X, X]),
+            ],
+        );
+    }
+}
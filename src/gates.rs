@@ -1,20 +1,144 @@
+use std::{fmt, str::FromStr};
+
+use crate::logic::{clamp_width, Logic};
+
 pub trait Gate<const INPUTS: usize, const OUTPUTS: usize> {
+    /// Name shown to the user.
     const NAME: &'static str;
+    /// Stable identifier of the gate type, used in circuit files. It must not
+    /// change once released, otherwise saved circuits can not be loaded.
+    const KIND: &'static str;
+    /// Ticks it takes a change of the inputs to reach the outputs, instances
+    /// placed in a simulation can override it.
+    const DELAY: u32 = 1;
 
-    fn update(&self, inputs: &[bool; INPUTS], outputs: &mut [bool; OUTPUTS]);
+    fn update(&self, inputs: &[Logic; INPUTS], outputs: &mut [Logic; OUTPUTS]);
 
     fn name(&self) -> &'static str {
         Self::NAME
     }
+
+    fn kind(&self) -> &'static str {
+        Self::KIND
+    }
+
+    fn delay(&self) -> u32 {
+        Self::DELAY
+    }
+
+    /// Configuration of this instance, which is needed to recreate it.
+    fn params(&self) -> Params {
+        Params::default()
+    }
+}
+
+/// Gate whose pins are known only at runtime, pins can be wider than one bit
+/// to carry a bus. Bits of all pins are passed to [`DynamicGate::update`]
+/// one pin after another.
+///
+/// The gate can keep internal state, like a register, whose outputs depend on
+/// earlier inputs as well. It is updated whenever any of its inputs changes,
+/// so clock edges can be detected by comparing the clock input with its
+/// previous value.
+pub trait DynamicGate {
+    fn name(&self) -> &'static str;
+
+    /// Stable identifier of the gate type, see [`Gate::KIND`].
+    fn kind(&self) -> &'static str;
+
+    fn input_widths(&self) -> Vec<usize>;
+
+    fn output_widths(&self) -> Vec<usize>;
+
+    fn update(&mut self, inputs: &[Logic], outputs: &mut [Logic]);
+
+    /// Ticks it takes a change of the inputs to reach the outputs, see
+    /// [`Gate::DELAY`].
+    fn delay(&self) -> u32 {
+        1
+    }
+
+    /// See [`Gate::params`].
+    fn params(&self) -> Params;
+
+    /// Words of a memory, empty for other gates.
+    fn memory(&self) -> &[u64] {
+        &[]
+    }
+
+    /// Overwrites a word of a memory, other gates ignore it.
+    fn write_memory(&mut self, _address: usize, _value: u64) {}
 }
 
+/// Named configuration values of a gate instance, kept as text so they can be
+/// written to circuit files as they are.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Params(Vec<(String, String)>);
+
+impl Params {
+    pub fn with(mut self, key: &str, value: impl ToString) -> Params {
+        self.set(key, value);
+        self
+    }
+
+    pub fn set(&mut self, key: &str, value: impl ToString) {
+        let value = value.to_string();
+        match self.0.iter_mut().find(|(k, _)| k == key) {
+            Some((_, v)) => *v = value,
+            None => self.0.push((key.to_owned(), value)),
+        }
+    }
+
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.0
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v.as_str())
+    }
+
+    /// Parses the value under `key`, falling back to `default` when missing.
+    pub fn get_or<T: FromStr>(&self, key: &str, default: T) -> Result<T, ParamError> {
+        match self.get(key) {
+            Some(value) => value.parse().map_err(|_| ParamError {
+                key: key.to_owned(),
+                value: value.to_owned(),
+            }),
+            None => Ok(default),
+        }
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.0.iter().map(|(k, v)| (k.as_str(), v.as_str()))
+    }
+}
+
+/// Parameter value which could not be parsed.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParamError {
+    pub key: String,
+    pub value: String,
+}
+
+impl fmt::Display for ParamError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "invalid value '{}' for parameter '{}'",
+            self.value, self.key
+        )
+    }
+}
+
+impl std::error::Error for ParamError {}
+
 pub struct And;
 
 impl Gate<2, 1> for And {
     const NAME: &'static str = "AND";
+    const KIND: &'static str = "and";
 
-    fn update(&self, inputs: &[bool; 2], outputs: &mut [bool; 1]) {
-        outputs[0] = inputs[0] && inputs[1];
+    fn update(&self, inputs: &[Logic; 2], outputs: &mut [Logic; 1]) {
+        outputs[0] = inputs[0] & inputs[1];
     }
 }
 
@@ -22,9 +146,10 @@ pub struct Nand;
 
 impl Gate<2, 1> for Nand {
     const NAME: &'static str = "NAND";
+    const KIND: &'static str = "nand";
 
-    fn update(&self, inputs: &[bool; 2], outputs: &mut [bool; 1]) {
-        outputs[0] = !(inputs[0] && inputs[1]);
+    fn update(&self, inputs: &[Logic; 2], outputs: &mut [Logic; 1]) {
+        outputs[0] = !(inputs[0] & inputs[1]);
     }
 }
 
@@ -32,9 +157,10 @@ pub struct Or;
 
 impl Gate<2, 1> for Or {
     const NAME: &'static str = "OR";
+    const KIND: &'static str = "or";
 
-    fn update(&self, inputs: &[bool; 2], outputs: &mut [bool; 1]) {
-        outputs[0] = inputs[0] || inputs[1];
+    fn update(&self, inputs: &[Logic; 2], outputs: &mut [Logic; 1]) {
+        outputs[0] = inputs[0] | inputs[1];
     }
 }
 
@@ -42,9 +168,10 @@ pub struct Nor;
 
 impl Gate<2, 1> for Nor {
     const NAME: &'static str = "NOR";
+    const KIND: &'static str = "nor";
 
-    fn update(&self, inputs: &[bool; 2], outputs: &mut [bool; 1]) {
-        outputs[0] = !(inputs[0] || inputs[1]);
+    fn update(&self, inputs: &[Logic; 2], outputs: &mut [Logic; 1]) {
+        outputs[0] = !(inputs[0] | inputs[1]);
     }
 }
 
@@ -52,9 +179,10 @@ pub struct Xor;
 
 impl Gate<2, 1> for Xor {
     const NAME: &'static str = "XOR";
+    const KIND: &'static str = "xor";
 
-    fn update(&self, inputs: &[bool; 2], outputs: &mut [bool; 1]) {
-        outputs[0] = inputs[0] != inputs[1];
+    fn update(&self, inputs: &[Logic; 2], outputs: &mut [Logic; 1]) {
+        outputs[0] = inputs[0] ^ inputs[1];
     }
 }
 
@@ -62,9 +190,115 @@ pub struct Xnor;
 
 impl Gate<2, 1> for Xnor {
     const NAME: &'static str = "XNOR";
+    const KIND: &'static str = "xnor";
 
-    fn update(&self, inputs: &[bool; 2], outputs: &mut [bool; 1]) {
-        outputs[0] = !(inputs[0] != inputs[1]);
+    fn update(&self, inputs: &[Logic; 2], outputs: &mut [Logic; 1]) {
+        outputs[0] = !(inputs[0] ^ inputs[1]);
+    }
+}
+
+/// Most inputs a [`Wide`] gate takes.
+pub const MAX_GATE_INPUTS: usize = 32;
+
+/// Two input gate which extends to more inputs, like an AND of all of them.
+pub trait MultiInput: Gate<2, 1> {
+    /// Output for any number of inputs, at least two.
+    fn combine(inputs: &[Logic]) -> Logic;
+}
+
+fn and_all(inputs: &[Logic]) -> Logic {
+    inputs.iter().fold(Logic::One, |all, input| all & *input)
+}
+
+fn or_all(inputs: &[Logic]) -> Logic {
+    inputs.iter().fold(Logic::Zero, |any, input| any | *input)
+}
+
+/// High for an odd number of high inputs.
+fn parity(inputs: &[Logic]) -> Logic {
+    inputs.iter().fold(Logic::Zero, |odd, input| odd ^ *input)
+}
+
+impl MultiInput for And {
+    fn combine(inputs: &[Logic]) -> Logic {
+        and_all(inputs)
+    }
+}
+
+impl MultiInput for Nand {
+    fn combine(inputs: &[Logic]) -> Logic {
+        !and_all(inputs)
+    }
+}
+
+impl MultiInput for Or {
+    fn combine(inputs: &[Logic]) -> Logic {
+        or_all(inputs)
+    }
+}
+
+impl MultiInput for Nor {
+    fn combine(inputs: &[Logic]) -> Logic {
+        !or_all(inputs)
+    }
+}
+
+impl MultiInput for Xor {
+    fn combine(inputs: &[Logic]) -> Logic {
+        parity(inputs)
+    }
+}
+
+impl MultiInput for Xnor {
+    fn combine(inputs: &[Logic]) -> Logic {
+        !parity(inputs)
+    }
+}
+
+/// Basic gate with the number of inputs chosen when it is placed. It keeps
+/// the name and kind of the two input gate, the number of inputs is stored in
+/// its `inputs` parameter.
+pub struct Wide<G> {
+    gate: G,
+    inputs: usize,
+}
+
+impl<G: MultiInput> Wide<G> {
+    pub fn new(gate: G, inputs: usize) -> Wide<G> {
+        Wide {
+            gate,
+            inputs: inputs.clamp(2, MAX_GATE_INPUTS),
+        }
+    }
+}
+
+impl<G: MultiInput> DynamicGate for Wide<G> {
+    fn name(&self) -> &'static str {
+        self.gate.name()
+    }
+
+    fn kind(&self) -> &'static str {
+        self.gate.kind()
+    }
+
+    fn input_widths(&self) -> Vec<usize> {
+        vec![1; self.inputs]
+    }
+
+    fn output_widths(&self) -> Vec<usize> {
+        vec![1]
+    }
+
+    fn update(&mut self, inputs: &[Logic], outputs: &mut [Logic]) {
+        outputs[0] = G::combine(inputs);
+    }
+
+    fn delay(&self) -> u32 {
+        self.gate.delay()
+    }
+
+    fn params(&self) -> Params {
+        Params::default().with("inputs", self.inputs)
     }
 }
 
@@ -72,8 +306,9 @@ pub struct Not;
 
 impl Gate<1, 1> for Not {
     const NAME: &'static str = "NOT";
+    const KIND: &'static str = "not";
 
-    fn update(&self, inputs: &[bool; 1], outputs: &mut [bool; 1]) {
+    fn update(&self, inputs: &[Logic; 1], outputs: &mut [Logic; 1]) {
         outputs[0] = !inputs[0];
     }
 }
@@ -82,34 +317,354 @@ pub struct Yes;
 
 impl Gate<1, 1> for Yes {
     const NAME: &'static str = "YES";
+    const KIND: &'static str = "yes";
+
+    fn update(&self, inputs: &[Logic; 1], outputs: &mut [Logic; 1]) {
+        outputs[0] = inputs[0].read();
+    }
+}
+
+/// Passes its first input through while the second one is high and releases
+/// the output otherwise, so several of them can share a bus.
+pub struct TriState;
+
+impl Gate<2, 1> for TriState {
+    const NAME: &'static str = "TRI";
+    const KIND: &'static str = "tri_state";
+
+    fn update(&self, inputs: &[Logic; 2], outputs: &mut [Logic; 1]) {
+        outputs[0] = match inputs[1] {
+            Logic::One => inputs[0].read(),
+            Logic::Zero => Logic::Z,
+            Logic::X | Logic::Z => Logic::X,
+        };
+    }
+}
+
+/// Drives its output with a fixed value. Unlike a switch it is not an input of
+/// the circuit, so it adds no column to truth tables or runner vectors.
+pub struct Constant {
+    value: bool,
+}
+
+impl Constant {
+    pub fn new(value: bool) -> Constant {
+        Constant { value }
+    }
+}
+
+impl Gate<0, 1> for Constant {
+    const NAME: &'static str = "CONST";
+    const KIND: &'static str = "constant";
+
+    fn update(&self, _inputs: &[Logic; 0], outputs: &mut [Logic; 1]) {
+        outputs[0] = self.value.into();
+    }
+
+    fn params(&self) -> Params {
+        Params::default().with("value", self.value)
+    }
+}
+
+/// Lights up while its input is high.
+pub struct Led;
+
+impl Gate<1, 0> for Led {
+    const NAME: &'static str = "LED";
+    const KIND: &'static str = "led";
+
+    fn update(&self, _inputs: &[Logic; 1], _outputs: &mut [Logic; 0]) {}
+}
 
-    fn update(&self, inputs: &[bool; 1], outputs: &mut [bool; 1]) {
-        outputs[0] = inputs[0];
+/// Shows each input as one segment, in the usual `a` to `g` order: top, top
+/// right, bottom right, bottom, bottom left, top left and middle.
+pub struct SevenSegment;
+
+impl Gate<7, 0> for SevenSegment {
+    const NAME: &'static str = "7SEG";
+    const KIND: &'static str = "seven_segment";
+
+    fn update(&self, _inputs: &[Logic; 7], _outputs: &mut [Logic; 0]) {}
+}
+
+/// Shows its 4 inputs as a hexadecimal digit, the first input is the least
+/// significant bit.
+pub struct HexDigit;
+
+impl HexDigit {
+    /// Segments to light up for the digit, in the same order as the inputs of
+    /// [`SevenSegment`]. All segments are unknown when any input is.
+    pub fn segments(inputs: &[Logic; 4]) -> [Logic; 7] {
+        #[rustfmt::skip]
+        const DIGITS: [u8; 16] = [
+            0x3f, 0x06, 0x5b, 0x4f, 0x66, 0x6d, 0x7d, 0x07,
+            0x7f, 0x6f, 0x77, 0x7c, 0x39, 0x5e, 0x79, 0x71,
+        ];
+
+        let Some(digit) = inputs.iter().rev().try_fold(0, |digit, input| {
+            Some((digit << 1) | input.to_bool()? as usize)
+        }) else {
+            return [Logic::X; 7];
+        };
+
+        std::array::from_fn(|segment| (DIGITS[digit] & (1 << segment) != 0).into())
     }
 }
 
+impl Gate<4, 0> for HexDigit {
+    const NAME: &'static str = "HEX";
+    const KIND: &'static str = "hex_digit";
+
+    fn update(&self, _inputs: &[Logic; 4], _outputs: &mut [Logic; 0]) {}
+}
+
+/// Splits a bus into single bits, the first output is the least significant
+/// bit.
+pub struct Splitter {
+    width: usize,
+}
+
+/// Joins single bits into a bus, the first input is the least significant
+/// bit.
+pub struct Merger {
+    width: usize,
+}
+
+impl Splitter {
+    pub const NAME: &'static str = "SPLIT";
+    pub const KIND: &'static str = "splitter";
+
+    pub fn new(width: usize) -> Splitter {
+        Splitter {
+            width: clamp_width(width),
+        }
+    }
+}
+
+impl Merger {
+    pub const NAME: &'static str = "MERGE";
+    pub const KIND: &'static str = "merger";
+
+    pub fn new(width: usize) -> Merger {
+        Merger {
+            width: clamp_width(width),
+        }
+    }
+}
+
+impl DynamicGate for Splitter {
+    fn name(&self) -> &'static str {
+        Self::NAME
+    }
+
+    fn kind(&self) -> &'static str {
+        Self::KIND
+    }
+
+    fn input_widths(&self) -> Vec<usize> {
+        vec![self.width]
+    }
+
+    fn output_widths(&self) -> Vec<usize> {
+        vec![1; self.width]
+    }
+
+    fn update(&mut self, inputs: &[Logic], outputs: &mut [Logic]) {
+        outputs.copy_from_slice(inputs);
+    }
+
+    fn params(&self) -> Params {
+        Params::default().with("width", self.width)
+    }
+}
+
+impl DynamicGate for Merger {
+    fn name(&self) -> &'static str {
+        Self::NAME
+    }
+
+    fn kind(&self) -> &'static str {
+        Self::KIND
+    }
+
+    fn input_widths(&self) -> Vec<usize> {
+        vec![1; self.width]
+    }
+
+    fn output_widths(&self) -> Vec<usize> {
+        vec![self.width]
+    }
+
+    fn update(&mut self, inputs: &[Logic], outputs: &mut [Logic]) {
+        outputs.copy_from_slice(inputs);
+    }
+
+    fn params(&self) -> Params {
+        Params::default().with("width", self.width)
+    }
+}
+
+/// A gate without inputs, its outputs are driven by internal state which can
+/// be changed by the user through [`Source::press`] and [`Source::release`].
+pub trait Source<const OUTPUTS: usize> {
+    /// Name shown to the user.
+    const NAME: &'static str;
+    /// Stable identifier of the source type, see [`Gate::KIND`].
+    const KIND: &'static str;
+
+    fn update(&mut self, outputs: &mut [Logic; OUTPUTS]);
+
+    fn press(&mut self) {}
+
+    fn release(&mut self) {}
+
+    fn name(&self) -> &'static str {
+        Self::NAME
+    }
+
+    fn kind(&self) -> &'static str {
+        Self::KIND
+    }
+
+    /// See [`Gate::params`].
+    fn params(&self) -> Params {
+        Params::default()
+    }
+}
+
+/// Flips its output every time it is pressed.
+#[derive(Default)]
+pub struct Switch {
+    on: bool,
+}
+
+impl Switch {
+    pub fn new(on: bool) -> Switch {
+        Switch { on }
+    }
+}
+
+impl Source<1> for Switch {
+    const NAME: &'static str = "SWITCH";
+    const KIND: &'static str = "switch";
+
+    fn update(&mut self, outputs: &mut [Logic; 1]) {
+        outputs[0] = self.on.into();
+    }
+
+    fn press(&mut self) {
+        self.on = !self.on;
+    }
+
+    fn params(&self) -> Params {
+        Params::default().with("on", self.on)
+    }
+}
+
+/// Outputs high only while it is held pressed.
+#[derive(Default)]
+pub struct Button {
+    pressed: bool,
+}
+
+impl Source<1> for Button {
+    const NAME: &'static str = "BUTTON";
+    const KIND: &'static str = "button";
+
+    fn update(&mut self, outputs: &mut [Logic; 1]) {
+        outputs[0] = self.pressed.into();
+    }
+
+    fn press(&mut self) {
+        self.pressed = true;
+    }
+
+    fn release(&mut self) {
+        self.pressed = false;
+    }
+}
+
+/// Produces a square wave counted in simulation ticks, the output is high for
+/// the first `high` ticks of every `period` ticks.
+pub struct Clock {
+    period: u32,
+    high: u32,
+    tick: u32,
+}
+
+impl Clock {
+    /// Creates a clock with `period` ticks long cycle, being high for
+    /// `duty_cycle` fraction of it. Both are clamped so the clock always
+    /// spends at least one tick in each state.
+    pub fn new(period: u32, duty_cycle: f32) -> Clock {
+        let period = period.max(2);
+        let high = ((period as f32 * duty_cycle).round() as u32).clamp(1, period - 1);
+
+        Clock {
+            period,
+            high,
+            tick: 0,
+        }
+    }
+}
+
+impl Default for Clock {
+    fn default() -> Clock {
+        Clock::new(2, 0.5)
+    }
+}
+
+impl Source<1> for Clock {
+    const NAME: &'static str = "CLOCK";
+    const KIND: &'static str = "clock";
+
+    fn update(&mut self, outputs: &mut [Logic; 1]) {
+        outputs[0] = (self.tick < self.high).into();
+        self.tick = (self.tick + 1) % self.period;
+    }
+
+    fn params(&self) -> Params {
+        Params::default()
+            .with("period", self.period)
+            .with("duty_cycle", self.high as f32 / self.period as f32)
+    }
+}
+
+/// Truth table helpers, shared with the tests of other gate modules.
 #[cfg(test)]
-mod tests {
+pub(crate) mod tests {
     use super::*;
 
     // test are just checks against truth tables
 
-    const Y: bool = true;
-    const N: bool = false;
+    pub(crate) const Y: Logic = Logic::One;
+    pub(crate) const N: Logic = Logic::Zero;
+    pub(crate) const X: Logic = Logic::X;
+    pub(crate) const Z: Logic = Logic::Z;
+
+    /// A different value than the given one, `!` keeps `X` as it is.
+    fn invert(value: Logic) -> Logic {
+        match value {
+            Logic::Zero => Logic::One,
+            Logic::One => Logic::Zero,
+            Logic::X => Logic::Z,
+            Logic::Z => Logic::X,
+        }
+    }
 
-    struct TruthTable<const INPUTS: usize, const OUTPUTS: usize, const ROWS: usize>(
-        [([bool; INPUTS], [bool; OUTPUTS]); ROWS],
+    pub(crate) struct TruthTable<const INPUTS: usize, const OUTPUTS: usize, const ROWS: usize>(
+        pub(crate) [([Logic; INPUTS], [Logic; OUTPUTS]); ROWS],
     );
 
-    fn test_gate<const INPUTS: usize, const OUTPUTS: usize>(
+    pub(crate) fn test_gate<const INPUTS: usize, const OUTPUTS: usize>(
         gate: impl Gate<INPUTS, OUTPUTS>,
-        io: ([bool; INPUTS], [bool; OUTPUTS]),
+        io: ([Logic; INPUTS], [Logic; OUTPUTS]),
     ) {
         let (inputs, expected_outputs) = io;
 
         // invert the expected outputs, so we always check changed values, some
-        // test may pass with default 'false' values
-        let mut outputs = expected_outputs.map(|output| !output);
+        // test may pass with default values
+        let mut outputs = expected_outputs.map(invert);
 
         gate.update(&inputs, &mut outputs);
 
@@ -124,6 +679,10 @@ mod tests {
             ([N, Y], [N]),
             ([Y, N], [N]),
             ([Y, Y], [Y]),
+            ([N, X], [N]),
+            ([Z, N], [N]),
+            ([Y, X], [X]),
+            ([Z, Y], [X]),
         ]);
 
         for row in table.0 {
@@ -139,6 +698,8 @@ mod tests {
             ([N, Y], [Y]),
             ([Y, N], [Y]),
             ([Y, Y], [N]),
+            ([X, N], [Y]),
+            ([Y, Z], [X]),
         ]);
 
         for row in table.0 {
@@ -154,6 +715,10 @@ mod tests {
             ([N, Y], [Y]),
             ([Y, N], [Y]),
             ([Y, Y], [Y]),
+            ([Y, X], [Y]),
+            ([Z, Y], [Y]),
+            ([N, X], [X]),
+            ([Z, N], [X]),
         ]);
 
         for row in table.0 {
@@ -169,6 +734,8 @@ mod tests {
             ([N, Y], [N]),
             ([Y, N], [N]),
             ([Y, Y], [N]),
+            ([X, Y], [N]),
+            ([N, Z], [X]),
         ]);
 
         for row in table.0 {
@@ -184,6 +751,8 @@ mod tests {
             ([N, Y], [Y]),
             ([Y, N], [Y]),
             ([Y, Y], [N]),
+            ([X, N], [X]),
+            ([Y, Z], [X]),
         ]);
 
         for row in table.0 {
@@ -199,6 +768,8 @@ mod tests {
             ([N, Y], [N]),
             ([Y, N], [N]),
             ([Y, Y], [Y]),
+            ([N, X], [X]),
+            ([Z, Y], [X]),
         ]);
 
         for row in table.0 {
@@ -212,6 +783,8 @@ mod tests {
         let table = TruthTable([
             ([N], [Y]),
             ([Y], [N]),
+            ([X], [X]),
+            ([Z], [X]),
         ]);
 
         for row in table.0 {
@@ -225,10 +798,243 @@ mod tests {
         let table = TruthTable([
             ([N], [N]),
             ([Y], [Y]),
+            ([X], [X]),
+            ([Z], [X]),
         ]);
 
         for row in table.0 {
             test_gate(Yes, row);
         }
     }
+
+    #[test]
+    fn tri_state() {
+        #[rustfmt::skip]
+        let table = TruthTable([
+            ([N, Y], [N]),
+            ([Y, Y], [Y]),
+            ([Z, Y], [X]),
+            ([N, N], [Z]),
+            ([Y, N], [Z]),
+            ([X, N], [Z]),
+            ([Y, X], [X]),
+            ([N, Z], [X]),
+        ]);
+
+        for row in table.0 {
+            test_gate(TriState, row);
+        }
+    }
+
+    #[test]
+    fn hex_digit() {
+        #[rustfmt::skip]
+        let table = TruthTable([
+            ([N, N, N, N], [Y, Y, Y, Y, Y, Y, N]),
+            ([Y, N, N, N], [N, Y, Y, N, N, N, N]),
+            ([N, Y, N, N], [Y, Y, N, Y, Y, N, Y]),
+            ([Y, Y, N, N], [Y, Y, Y, Y, N, N, Y]),
+            ([N, N, Y, N], [N, Y, Y, N, N, Y, Y]),
+            ([Y, N, Y, N], [Y, N, Y, Y, N, Y, Y]),
+            ([N, Y, Y, N], [Y, N, Y, Y, Y, Y, Y]),
+            ([Y, Y, Y, N], [Y, Y, Y, N, N, N, N]),
+            ([N, N, N, Y], [Y, Y, Y, Y, Y, Y, Y]),
+            ([Y, N, N, Y], [Y, Y, Y, Y, N, Y, Y]),
+            ([N, Y, N, Y], [Y, Y, Y, N, Y, Y, Y]),
+            ([Y, Y, N, Y], [N, N, Y, Y, Y, Y, Y]),
+            ([N, N, Y, Y], [Y, N, N, Y, Y, Y, N]),
+            ([Y, N, Y, Y], [N, Y, Y, Y, Y, N, Y]),
+            ([N, Y, Y, Y], [Y, N, N, Y, Y, Y, Y]),
+            ([Y, Y, Y, Y], [Y, N, N, N, Y, Y, Y]),
+        ]);
+
+        for (inputs, segments) in table.0 {
+            assert_eq!(HexDigit::segments(&inputs), segments);
+        }
+
+        assert_eq!(HexDigit::segments(&[Y, N, Z, N]), [X; 7]);
+    }
+
+    pub(crate) fn test_dynamic_gate(
+        mut gate: impl DynamicGate,
+        (inputs, expected_outputs): (&[Logic], &[Logic]),
+    ) {
+        let input_width: usize = gate.input_widths().iter().sum();
+        let output_width: usize = gate.output_widths().iter().sum();
+        assert_eq!(
+            (inputs.len(), expected_outputs.len()),
+            (input_width, output_width)
+        );
+
+        let mut outputs: Vec<_> = expected_outputs.iter().copied().map(invert).collect();
+
+        gate.update(inputs, &mut outputs);
+
+        assert_eq!(outputs, expected_outputs);
+    }
+
+    #[test]
+    fn constant() {
+        test_gate(Constant::new(false), ([], [N]));
+        test_gate(Constant::new(true), ([], [Y]));
+    }
+
+    #[test]
+    fn splitter() {
+        let splitter = Splitter::new(3);
+        assert_eq!(splitter.input_widths(), [3]);
+        assert_eq!(splitter.output_widths(), [1, 1, 1]);
+        assert_eq!(Splitter::new(100_000_000_000).input_widths(), [64]);
+
+        for row in [([N, Y, Y], [N, Y, Y]), ([Y, X, Z], [Y, X, Z])] {
+            test_dynamic_gate(Splitter::new(3), (&row.0, &row.1));
+        }
+    }
+
+    #[test]
+    fn wide() {
+        let and = Wide::new(And, 4);
+        assert_eq!(and.input_widths(), [1; 4]);
+        assert_eq!(and.kind(), "and");
+        assert_eq!(Wide::new(Or, 100).input_widths().len(), MAX_GATE_INPUTS);
+
+        #[rustfmt::skip]
+        let table = [
+            ([Y, Y, Y], [[Y], [N], [Y], [N], [Y], [N]]),
+            ([Y, N, Y], [[N], [Y], [Y], [N], [N], [Y]]),
+            ([N, N, N], [[N], [Y], [N], [Y], [N], [Y]]),
+            ([X, Y, N], [[N], [Y], [Y], [N], [X], [X]]),
+            ([Z, Y, Y], [[X], [X], [Y], [N], [X], [X]]),
+        ];
+
+        for (inputs, [and, nand, or, nor, xor, xnor]) in table {
+            test_dynamic_gate(Wide::new(And, 3), (&inputs, &and));
+            test_dynamic_gate(Wide::new(Nand, 3), (&inputs, &nand));
+            test_dynamic_gate(Wide::new(Or, 3), (&inputs, &or));
+            test_dynamic_gate(Wide::new(Nor, 3), (&inputs, &nor));
+            test_dynamic_gate(Wide::new(Xor, 3), (&inputs, &xor));
+            test_dynamic_gate(Wide::new(Xnor, 3), (&inputs, &xnor));
+        }
+    }
+
+    #[test]
+    fn merger() {
+        let merger = Merger::new(4);
+        assert_eq!(merger.input_widths(), [1, 1, 1, 1]);
+        assert_eq!(merger.output_widths(), [4]);
+        assert_eq!(Merger::new(100_000_000_000).output_widths(), [64]);
+
+        for row in [([N, Y, Y, N], [N, Y, Y, N]), ([Y, N, N, Y], [Y, N, N, Y])] {
+            test_dynamic_gate(Merger::new(4), (&row.0, &row.1));
+        }
+    }
+
+    // sources are checked against sequences of interactions, each step is
+    // the interaction to apply and the output expected after it
+
+    #[derive(Clone, Copy)]
+    enum Interaction {
+        Press,
+        Release,
+        Nothing,
+    }
+
+    use Interaction::*;
+
+    fn test_source<const OUTPUTS: usize>(
+        mut source: impl Source<OUTPUTS>,
+        sequence: &[(Interaction, [Logic; OUTPUTS])],
+    ) {
+        for (interaction, expected_outputs) in sequence {
+            match interaction {
+                Press => source.press(),
+                Release => source.release(),
+                Nothing => {}
+            }
+
+            let mut outputs = expected_outputs.map(invert);
+
+            source.update(&mut outputs);
+
+            assert_eq!(&outputs, expected_outputs);
+        }
+    }
+
+    #[test]
+    fn switch() {
+        test_source(
+            Switch::default(),
+            &[
+                (Nothing, [N]),
+                (Press, [Y]),
+                (Release, [Y]),
+                (Nothing, [Y]),
+                (Press, [N]),
+                (Release, [N]),
+            ],
+        );
+    }
+
+    #[test]
+    fn clock() {
+        test_source(
+            Clock::default(),
+            &[(Nothing, [Y]), (Nothing, [N]), (Press, [Y]), (Release, [N])],
+        );
+
+        test_source(
+            Clock::new(4, 0.25),
+            &[
+                (Nothing, [Y]),
+                (Nothing, [N]),
+                (Nothing, [N]),
+                (Nothing, [N]),
+                (Nothing, [Y]),
+            ],
+        );
+
+        test_source(
+            Clock::new(5, 0.6),
+            &[
+                (Nothing, [Y]),
+                (Nothing, [Y]),
+                (Nothing, [Y]),
+                (Nothing, [N]),
+                (Nothing, [N]),
+                (Nothing, [Y]),
+            ],
+        );
+
+        // degenerate configurations still toggle
+        test_source(
+            Clock::new(0, 1.0),
+            &[(Nothing, [Y]), (Nothing, [N]), (Nothing, [Y])],
+        );
+    }
+
+    #[test]
+    fn params() {
+        let params = Clock::new(10, 0.3).params();
+        assert_eq!(params.get("period"), Some("10"));
+        assert_eq!(params.get_or("duty_cycle", 0f32), Ok(0.3));
+        assert_eq!(params.get_or("missing", 7u32), Ok(7));
+
+        let params = params.with("period", "fast");
+        assert!(params.get_or("period", 0u32).is_err());
+        assert_eq!(params.iter().count(), 2);
+    }
+
+    #[test]
+    fn button() {
+        test_source(
+            Button::default(),
+            &[
+                (Nothing, [N]),
+                (Press, [Y]),
+                (Nothing, [Y]),
+                (Release, [N]),
+                (Nothing, [N]),
+            ],
+        );
+    }
 }
@@ -0,0 +1,47 @@
+//! Simulation engine of logic-sim, without any graphics.
+//!
+//! Circuits are built either directly in a [`LogicSimulation`] from gates
+//! implementing [`Gate`], [`Source`] or [`DynamicGate`], or loaded from a
+//! [`Circuit`], the board model the editor saves and loads as plain text:
+//!
+//! ```
+//! use logic_sim::{gates::{And, Switch}, Logic, LogicSimulation};
+//!
+//! let mut sim = LogicSimulation::new();
+//! let a = sim.add_source(Switch::new(true));
+//! let b = sim.add_source(Switch::new(true));
+//! let and = sim.add_gate(And);
+//! sim.add_connection(a, 0, and, 0).unwrap();
+//! sim.add_connection(b, 0, and, 1).unwrap();
+//!
+//! sim.settle(100).unwrap();
+//! assert_eq!(sim.get_output(and, 0).unwrap(), [Logic::One]);
+//! ```
+//!
+//! The editor is the `logic-sim` binary behind the default `gui` feature,
+//! the `logic-sim-cli` binary runs [`cli`] without it.
+
+pub mod arena;
+pub mod arithmetic;
+pub mod circuit_file;
+pub mod cli;
+pub mod component;
+pub mod expression;
+pub mod extraction;
+pub mod flip_flops;
+pub mod gates;
+pub mod logic;
+pub mod logic_simulation;
+pub mod memory;
+pub mod plexers;
+pub mod probe;
+pub mod registers;
+pub mod registry;
+pub mod synthesis;
+pub mod truth_table;
+pub mod vcd;
+
+pub use circuit_file::Circuit;
+pub use gates::{DynamicGate, Gate, Source};
+pub use logic::Logic;
+pub use logic_simulation::LogicSimulation;
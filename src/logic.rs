@@ -0,0 +1,135 @@
+use std::{
+    fmt,
+    ops::{BitAnd, BitOr, BitXor, Not},
+};
+
+/// Value of a single bit of a pin.
+///
+/// Gates treat a floating input the same as an unknown one, so `Z` entering a
+/// gate comes out as `X`. Only gates which release their outputs, like
+/// [`crate::gates::TriState`], produce `Z`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Logic {
+    Zero,
+    One,
+    /// Unknown value, either not driven yet or driven to both values at once.
+    #[default]
+    X,
+    /// High impedance, nothing drives the bit.
+    Z,
+}
+
+impl Logic {
+    /// The value as a `bool`, `None` for `X` and `Z`.
+    pub fn to_bool(self) -> Option<bool> {
+        match self {
+            Logic::Zero => Some(false),
+            Logic::One => Some(true),
+            Logic::X | Logic::Z => None,
+        }
+    }
+
+    pub fn is_high(self) -> bool {
+        self == Logic::One
+    }
+
+    /// The value as a gate input reads it, a floating `Z` reads as `X`.
+    pub fn read(self) -> Logic {
+        match self {
+            Logic::Z => Logic::X,
+            value => value,
+        }
+    }
+}
+
+/// Value of a bus whose first bit is the least significant one, `None` when
+/// any bit is unknown or floating. Bits past the 64th are ignored.
+pub fn bus_value(bits: &[Logic]) -> Option<u64> {
+    bits.iter().take(64).rev().try_fold(0, |value, bit| {
+        bit.to_bool().map(|bit| (value << 1) | bit as u64)
+    })
+}
+
+/// Widest bus gates take, the most bits [`bus_value`] reads.
+pub const MAX_BUS_WIDTH: usize = 64;
+
+/// Width of a bus limited to 1 to [`MAX_BUS_WIDTH`] bits, so a width read
+/// from a circuit file can not exhaust memory.
+pub fn clamp_width(width: usize) -> usize {
+    width.clamp(1, MAX_BUS_WIDTH)
+}
+
+/// Sets the bits of a bus to the value, see [`bus_value`].
+pub fn set_bus_value(bits: &mut [Logic], value: u64) {
+    for (index, bit) in bits.iter_mut().enumerate() {
+        *bit = (index < 64 && value >> index & 1 == 1).into();
+    }
+}
+
+impl From<bool> for Logic {
+    fn from(value: bool) -> Logic {
+        match value {
+            false => Logic::Zero,
+            true => Logic::One,
+        }
+    }
+}
+
+impl Not for Logic {
+    type Output = Logic;
+
+    fn not(self) -> Logic {
+        match self.to_bool() {
+            Some(value) => (!value).into(),
+            None => Logic::X,
+        }
+    }
+}
+
+impl BitAnd for Logic {
+    type Output = Logic;
+
+    /// `0` wins over unknown inputs.
+    fn bitand(self, other: Logic) -> Logic {
+        match (self.read(), other.read()) {
+            (Logic::Zero, _) | (_, Logic::Zero) => Logic::Zero,
+            (Logic::One, Logic::One) => Logic::One,
+            _ => Logic::X,
+        }
+    }
+}
+
+impl BitOr for Logic {
+    type Output = Logic;
+
+    /// `1` wins over unknown inputs.
+    fn bitor(self, other: Logic) -> Logic {
+        match (self.read(), other.read()) {
+            (Logic::One, _) | (_, Logic::One) => Logic::One,
+            (Logic::Zero, Logic::Zero) => Logic::Zero,
+            _ => Logic::X,
+        }
+    }
+}
+
+impl BitXor for Logic {
+    type Output = Logic;
+
+    fn bitxor(self, other: Logic) -> Logic {
+        match (self.to_bool(), other.to_bool()) {
+            (Some(a), Some(b)) => (a != b).into(),
+            _ => Logic::X,
+        }
+    }
+}
+
+impl fmt::Display for Logic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Logic::Zero => "0",
+            Logic::One => "1",
+            Logic::X => "X",
+            Logic::Z => "Z",
+        })
+    }
+}
@@ -1,112 +1,1494 @@
-use std::collections::HashMap;
+use std::{borrow::Cow, collections::BTreeMap, fmt, str::FromStr};
 
-use crate::gates::Gate;
+use crate::{
+    arena::Arena,
+    gates::{DynamicGate, Gate, Params, Source},
+    logic::Logic,
+    probe::{Probe, ProbePin},
+};
 
-type UpdateFn = Box<dyn Fn(&[bool], &mut [bool])>;
+/// Type erased gate, lets the simulation store gates and sources together.
+pub(crate) trait Component {
+    fn update(&mut self, inputs: &[Logic], outputs: &mut [Logic]);
+
+    fn params(&self) -> Params;
+
+    fn press(&mut self) {}
+
+    fn release(&mut self) {}
+
+    fn memory(&self) -> &[u64] {
+        &[]
+    }
+
+    fn write_memory(&mut self, _address: usize, _value: u64) {}
+
+    /// Propagation delay of the component type in ticks, instances can
+    /// override it.
+    fn delay(&self) -> u32 {
+        1
+    }
+
+    /// Stateful components are updated every tick, others only when their
+    /// inputs change.
+    fn is_stateful(&self) -> bool {
+        true
+    }
+}
+
+struct GateComponent<G, const INPUTS: usize, const OUTPUTS: usize>(G);
+
+impl<G, const INPUTS: usize, const OUTPUTS: usize> Component for GateComponent<G, INPUTS, OUTPUTS>
+where
+    G: Gate<INPUTS, OUTPUTS>,
+{
+    fn update(&mut self, inputs: &[Logic], outputs: &mut [Logic]) {
+        self.0
+            .update(inputs.try_into().unwrap(), outputs.try_into().unwrap())
+    }
+
+    fn params(&self) -> Params {
+        self.0.params()
+    }
+
+    fn delay(&self) -> u32 {
+        self.0.delay()
+    }
+
+    fn is_stateful(&self) -> bool {
+        false
+    }
+}
+
+struct SourceComponent<S, const OUTPUTS: usize>(S);
+
+impl<S, const OUTPUTS: usize> Component for SourceComponent<S, OUTPUTS>
+where
+    S: Source<OUTPUTS>,
+{
+    fn update(&mut self, _inputs: &[Logic], outputs: &mut [Logic]) {
+        self.0.update(outputs.try_into().unwrap())
+    }
+
+    fn params(&self) -> Params {
+        self.0.params()
+    }
+
+    fn press(&mut self) {
+        self.0.press()
+    }
+
+    fn release(&mut self) {
+        self.0.release()
+    }
+}
+
+struct DynamicComponent<G>(G);
+
+impl<G: DynamicGate> Component for DynamicComponent<G> {
+    fn update(&mut self, inputs: &[Logic], outputs: &mut [Logic]) {
+        self.0.update(inputs, outputs)
+    }
+
+    fn params(&self) -> Params {
+        self.0.params()
+    }
+
+    fn memory(&self) -> &[u64] {
+        self.0.memory()
+    }
+
+    fn write_memory(&mut self, address: usize, value: u64) {
+        self.0.write_memory(address, value)
+    }
+
+    fn delay(&self) -> u32 {
+        self.0.delay()
+    }
+
+    /// State changes only with the inputs, so there is no need to update it
+    /// every tick.
+    fn is_stateful(&self) -> bool {
+        false
+    }
+}
+
+/// Gate or source with its type erased, so gates can be created from data
+/// known only at runtime, like a kind read from a circuit file.
+pub struct BoxedGate {
+    input_widths: Vec<usize>,
+    output_widths: Vec<usize>,
+    delay: u32,
+    component: Box<dyn Component>,
+    name: Cow<'static, str>,
+    kind: &'static str,
+}
+
+impl BoxedGate {
+    pub fn gate<const INPUTS: usize, const OUTPUTS: usize>(
+        gate: impl Gate<INPUTS, OUTPUTS> + 'static,
+    ) -> BoxedGate {
+        BoxedGate {
+            input_widths: vec![1; INPUTS],
+            output_widths: vec![1; OUTPUTS],
+            delay: gate.delay(),
+            name: gate.name().into(),
+            kind: gate.kind(),
+            component: Box::new(GateComponent(gate)),
+        }
+    }
+
+    pub fn source<const OUTPUTS: usize>(source: impl Source<OUTPUTS> + 'static) -> BoxedGate {
+        BoxedGate {
+            input_widths: Vec::new(),
+            output_widths: vec![1; OUTPUTS],
+            delay: 1,
+            name: source.name().into(),
+            kind: source.kind(),
+            component: Box::new(SourceComponent(source)),
+        }
+    }
+
+    pub fn dynamic(gate: impl DynamicGate + 'static) -> BoxedGate {
+        BoxedGate {
+            input_widths: gate.input_widths(),
+            output_widths: gate.output_widths(),
+            delay: gate.delay(),
+            name: gate.name().into(),
+            kind: gate.kind(),
+            component: Box::new(DynamicComponent(gate)),
+        }
+    }
+
+    /// Wraps a component whose pins are known only at runtime.
+    pub(crate) fn component(
+        input_widths: Vec<usize>,
+        output_widths: Vec<usize>,
+        name: String,
+        kind: &'static str,
+        component: Box<dyn Component>,
+    ) -> BoxedGate {
+        BoxedGate {
+            input_widths,
+            output_widths,
+            delay: component.delay(),
+            name: name.into(),
+            kind,
+            component,
+        }
+    }
+
+    pub fn delay(&self) -> u32 {
+        self.delay
+    }
+
+    /// Saves the gate as another kind, like the kind it is registered as.
+    pub(crate) fn with_kind(mut self, kind: &'static str) -> BoxedGate {
+        self.kind = kind;
+        self
+    }
+
+    /// Overrides the propagation delay of this instance, it is at least one
+    /// tick.
+    pub fn with_delay(mut self, delay: u32) -> BoxedGate {
+        self.delay = delay.max(1);
+        self
+    }
+}
+
+/// Values of all inputs or all outputs of a gate, bits of every pin follow
+/// each other.
+#[derive(Clone)]
+struct Pins {
+    bits: Box<[Logic]>,
+    /// First bit of every pin, followed by the total number of bits.
+    offsets: Box<[usize]>,
+}
+
+impl Pins {
+    fn new(widths: &[usize], value: Logic) -> Pins {
+        let offsets: Box<[usize]> = std::iter::once(0)
+            .chain(widths.iter().scan(0, |offset, width| {
+                *offset += width;
+                Some(*offset)
+            }))
+            .collect();
+
+        Pins {
+            bits: vec![value; offsets[widths.len()]].into_boxed_slice(),
+            offsets,
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.offsets.len() - 1
+    }
+
+    fn width(&self, pin: usize) -> usize {
+        self.offsets[pin + 1] - self.offsets[pin]
+    }
+
+    fn widths(&self) -> Vec<usize> {
+        (0..self.len()).map(|pin| self.width(pin)).collect()
+    }
+
+    fn pin(&self, pin: usize) -> &[Logic] {
+        &self.bits[self.offsets[pin]..self.offsets[pin + 1]]
+    }
+
+    fn pin_mut(&mut self, pin: usize) -> &mut [Logic] {
+        &mut self.bits[self.offsets[pin]..self.offsets[pin + 1]]
+    }
+}
 
 struct GateState {
-    inputs: Box<[bool]>,
-    outputs: Box<[bool]>,
-    update_fn: UpdateFn,
-    name: &'static str,
+    inputs: Pins,
+    outputs: Pins,
+    /// Outputs once all scheduled output changes are applied.
+    projected: Pins,
+    delay: u32,
+    component: Box<dyn Component>,
+    name: Cow<'static, str>,
+    kind: &'static str,
+    /// Inputs of other gates connected to each output.
+    fanout: Box<[Vec<(usize, usize)>]>,
+    /// Outputs of other gates connected to each input.
+    drivers: Box<[Vec<(usize, usize)>]>,
+    /// Inputs whose drivers disagree, see [`NetMode::Contention`].
+    conflicts: Box<[bool]>,
+    stateful: bool,
+    scheduled: bool,
 }
 
 impl GateState {
     fn update(&mut self) {
-        (self.update_fn)(&self.inputs, &mut self.outputs);
+        self.component
+            .update(&self.inputs.bits, &mut self.projected.bits);
     }
 }
 
+/// Scheduled change of an output: gate, output index and its new bits.
+type OutputEvent = (usize, usize, Box<[Logic]>);
+
+/// Connection from an output to an input: output gate, output index, input
+/// gate and input index.
+pub type Connection = (usize, usize, usize, usize);
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SimError {
+    UnknownGate(usize),
+    InputOutOfRange {
+        gate: usize,
+        input: usize,
+    },
+    OutputOutOfRange {
+        gate: usize,
+        output: usize,
+    },
+    DuplicateConnection(Connection),
+    WidthMismatch {
+        output: usize,
+        input: usize,
+    },
+    UnknownConnection(Connection),
+    /// A second driver of the input, which [`NetMode::Exclusive`] forbids.
+    MultipleDrivers {
+        gate: usize,
+        input: usize,
+    },
+    UnknownProbe(usize),
+}
+
+impl fmt::Display for SimError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SimError::UnknownGate(id) => write!(f, "unknown gate {id}"),
+            SimError::InputOutOfRange { gate, input } => {
+                write!(f, "gate {gate} has no input {input}")
+            }
+            SimError::OutputOutOfRange { gate, output } => {
+                write!(f, "gate {gate} has no output {output}")
+            }
+            SimError::DuplicateConnection((from, output, to, input)) => write!(
+                f,
+                "output {output} of gate {from} is already connected to input {input} of gate {to}"
+            ),
+            SimError::WidthMismatch { output, input } => write!(
+                f,
+                "can not connect {output} bits wide output to {input} bits wide input"
+            ),
+            SimError::UnknownConnection((from, output, to, input)) => write!(
+                f,
+                "output {output} of gate {from} is not connected to input {input} of gate {to}"
+            ),
+            SimError::MultipleDrivers { gate, input } => {
+                write!(f, "input {input} of gate {gate} already has a driver")
+            }
+            SimError::UnknownProbe(id) => write!(f, "unknown probe {id}"),
+        }
+    }
+}
+
+impl std::error::Error for SimError {}
+
+/// How an input driven by several outputs resolves its value. Drivers
+/// releasing a bit with [`Logic::Z`] take no part, so a bit without any
+/// other driver floats at `Z` in every mode.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum NetMode {
+    /// Every input has at most one driver, further connections are rejected.
+    Exclusive,
+    /// A bit is high when any driver sets it.
+    #[default]
+    WiredOr,
+    /// A bit is high when all drivers set it.
+    WiredAnd,
+    /// Drivers have to agree, otherwise the input is flagged as conflicted
+    /// and the disagreeing bits read `X`.
+    Contention,
+}
+
+impl NetMode {
+    pub const ALL: [NetMode; 4] = [
+        NetMode::Exclusive,
+        NetMode::WiredOr,
+        NetMode::WiredAnd,
+        NetMode::Contention,
+    ];
+
+    /// Combines the bit already on the net with one more driver, returns
+    /// whether the driver disagrees with the others.
+    fn combine(self, net: Logic, driver: Logic) -> (Logic, bool) {
+        match (net, driver) {
+            (net, Logic::Z) => (net, false),
+            (Logic::Z, driver) => (driver, false),
+            (net, driver) => match self {
+                NetMode::Exclusive | NetMode::WiredOr => (net | driver, false),
+                NetMode::WiredAnd => (net & driver, false),
+                NetMode::Contention => (if net == driver { net } else { Logic::X }, net != driver),
+            },
+        }
+    }
+
+    /// Stable name used in circuit files.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            NetMode::Exclusive => "exclusive",
+            NetMode::WiredOr => "wired_or",
+            NetMode::WiredAnd => "wired_and",
+            NetMode::Contention => "contention",
+        }
+    }
+}
+
+impl fmt::Display for NetMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            NetMode::Exclusive => "single driver",
+            NetMode::WiredOr => "wired OR",
+            NetMode::WiredAnd => "wired AND",
+            NetMode::Contention => "contention",
+        })
+    }
+}
+
+impl FromStr for NetMode {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<NetMode, ()> {
+        NetMode::ALL
+            .into_iter()
+            .find(|mode| mode.as_str() == s)
+            .ok_or(())
+    }
+}
+
+/// Event driven simulation where every gate has a propagation delay counted
+/// in ticks, one tick unless set otherwise.
+///
+/// Each tick first recomputes inputs whose connected outputs changed during
+/// the previous tick, then updates gates whose inputs changed together with
+/// all stateful gates. Output changes made by an update are applied at the
+/// end of the tick the gate delay later, and become the events for the tick
+/// after it. A circuit which settled costs only its stateful gates.
+///
+/// Delays are transport delays, so a pulse shorter than the delay of a gate
+/// still passes through it, which makes glitches and hazards visible.
+///
+/// Inputs with several drivers are resolved according to the [`NetMode`].
 pub struct LogicSimulation {
-    counter: usize,
-    gates: HashMap<usize, GateState>,
-    connections: Vec<(usize, usize, usize, usize)>,
+    gates: Arena<GateState>,
+    connections: Vec<Connection>,
+    net_mode: NetMode,
+    /// Number of ticks simulated so far.
+    time: u64,
+    /// Output changes keyed by the tick at whose end they are applied.
+    output_events: BTreeMap<u64, Vec<OutputEvent>>,
+    /// Inputs to recompute at the start of the next tick.
+    input_events: Vec<(usize, usize)>,
+    /// Gates to update in the next tick, stateful gates are always updated.
+    scheduled: Vec<usize>,
+    stateful: Vec<usize>,
+    /// Reused buffer for combining bits of connected outputs.
+    scratch: Vec<Logic>,
+    /// Gates whose outputs changed at the end of the last tick.
+    changed: Vec<usize>,
+    probes: Arena<Probe>,
 }
 
 impl LogicSimulation {
     pub fn new() -> LogicSimulation {
         LogicSimulation {
-            counter: 0,
-            gates: HashMap::new(),
+            gates: Arena::new(),
             connections: Vec::new(),
+            net_mode: NetMode::default(),
+            time: 0,
+            output_events: BTreeMap::new(),
+            input_events: Vec::new(),
+            scheduled: Vec::new(),
+            stateful: Vec::new(),
+            scratch: Vec::new(),
+            changed: Vec::new(),
+            probes: Arena::new(),
         }
     }
+}
+
+impl Default for LogicSimulation {
+    fn default() -> LogicSimulation {
+        LogicSimulation::new()
+    }
+}
 
+impl LogicSimulation {
     pub fn add_gate<const INPUTS: usize, const OUTPUTS: usize>(
         &mut self,
         gate: impl Gate<INPUTS, OUTPUTS> + 'static,
     ) -> usize {
-        let inputs = Box::new([false; INPUTS]);
-        let outputs = Box::new([false; OUTPUTS]);
-        let id = self.counter;
-        let name = gate.name();
+        self.add_boxed_gate(BoxedGate::gate(gate))
+    }
+
+    pub fn add_source<const OUTPUTS: usize>(
+        &mut self,
+        source: impl Source<OUTPUTS> + 'static,
+    ) -> usize {
+        self.add_boxed_gate(BoxedGate::source(source))
+    }
+
+    pub fn add_dynamic_gate(&mut self, gate: impl DynamicGate + 'static) -> usize {
+        self.add_boxed_gate(BoxedGate::dynamic(gate))
+    }
+
+    pub fn add_boxed_gate(&mut self, gate: BoxedGate) -> usize {
+        let stateful = gate.component.is_stateful();
+        // nothing drives the inputs yet and outputs are unknown until the
+        // first update
+        let outputs = Pins::new(&gate.output_widths, Logic::X);
 
-        let update_fn: UpdateFn = Box::new(move |inputs, outputs| {
-            gate.update(inputs.try_into().unwrap(), outputs.try_into().unwrap())
+        let id = self.gates.insert(GateState {
+            inputs: Pins::new(&gate.input_widths, Logic::Z),
+            projected: outputs.clone(),
+            outputs,
+            delay: gate.delay,
+            component: gate.component,
+            name: gate.name,
+            kind: gate.kind,
+            fanout: vec![Vec::new(); gate.output_widths.len()].into_boxed_slice(),
+            drivers: vec![Vec::new(); gate.input_widths.len()].into_boxed_slice(),
+            conflicts: vec![false; gate.input_widths.len()].into_boxed_slice(),
+            stateful,
+            scheduled: false,
         });
 
-        self.gates.insert(
-            id,
-            GateState {
-                inputs,
-                outputs,
-                update_fn,
-                name,
-            },
-        );
-        self.counter += 1;
+        // even without inputs the gate may need to change its outputs, like
+        // NOT gate does
+        if stateful {
+            self.stateful.push(id);
+        } else {
+            self.schedule(id);
+        }
+
         id
     }
 
-    pub fn remove_gate(&mut self, id: usize) {
-        if let Some(_) = self.gates.remove(&id) {
-            self.connections
-                .retain(|(output_gate_id, _, input_gate_id, _)| {
-                    *output_gate_id != id && *input_gate_id != id
+    pub fn remove_gate(&mut self, id: usize) -> Result<(), SimError> {
+        let state = self.gates.remove(id).ok_or(SimError::UnknownGate(id))?;
+
+        for (gate_id, input) in state.fanout.iter().flatten() {
+            if let Some(gate) = self.gates.get_mut(*gate_id) {
+                gate.drivers[*input].retain(|(driver_id, _)| *driver_id != id);
+            }
+            self.input_events.push((*gate_id, *input));
+        }
+
+        for (gate_id, output) in state.drivers.iter().flatten() {
+            if let Some(gate) = self.gates.get_mut(*gate_id) {
+                gate.fanout[*output].retain(|(target_id, _)| *target_id != id);
+            }
+        }
+
+        // the id may be reused by the next added gate
+        self.scheduled.retain(|gate_id| *gate_id != id);
+        for events in self.output_events.values_mut() {
+            events.retain(|(gate_id, ..)| *gate_id != id);
+        }
+        self.stateful.retain(|gate_id| *gate_id != id);
+        let probes: Vec<_> = self
+            .probes
+            .iter()
+            .filter(|(_, probe)| probe.pin().gate() == id)
+            .map(|(probe_id, _)| probe_id)
+            .collect();
+        for probe_id in probes {
+            self.probes.remove(probe_id);
+        }
+        self.connections
+            .retain(|(output_gate_id, _, input_gate_id, _)| {
+                *output_gate_id != id && *input_gate_id != id
+            });
+
+        Ok(())
+    }
+
+    fn gate(&self, id: usize) -> Result<&GateState, SimError> {
+        self.gates.get(id).ok_or(SimError::UnknownGate(id))
+    }
+
+    fn gate_mut(&mut self, id: usize) -> Result<&mut GateState, SimError> {
+        self.gates.get_mut(id).ok_or(SimError::UnknownGate(id))
+    }
+
+    fn check_output(&self, gate: usize, output: usize) -> Result<(), SimError> {
+        match output < self.gate(gate)?.outputs.len() {
+            true => Ok(()),
+            false => Err(SimError::OutputOutOfRange { gate, output }),
+        }
+    }
+
+    fn check_input(&self, gate: usize, input: usize) -> Result<(), SimError> {
+        match input < self.gate(gate)?.inputs.len() {
+            true => Ok(()),
+            false => Err(SimError::InputOutOfRange { gate, input }),
+        }
+    }
+
+    pub fn add_connection(
+        &mut self,
+        from: usize,
+        output: usize,
+        to: usize,
+        input: usize,
+    ) -> Result<(), SimError> {
+        self.check_output(from, output)?;
+        self.check_input(to, input)?;
+
+        let (output_width, input_width) = (
+            self.gates[from].outputs.width(output),
+            self.gates[to].inputs.width(input),
+        );
+        if output_width != input_width {
+            return Err(SimError::WidthMismatch {
+                output: output_width,
+                input: input_width,
+            });
+        }
+
+        let connection = (from, output, to, input);
+        if self.connections.contains(&connection) {
+            return Err(SimError::DuplicateConnection(connection));
+        }
+
+        if self.net_mode == NetMode::Exclusive && !self.gates[to].drivers[input].is_empty() {
+            return Err(SimError::MultipleDrivers { gate: to, input });
+        }
+
+        self.gates[from].fanout[output].push((to, input));
+        self.gates[to].drivers[input].push((from, output));
+        self.input_events.push((to, input));
+        self.connections.push(connection);
+        Ok(())
+    }
+
+    pub fn remove_connection(
+        &mut self,
+        from: usize,
+        output: usize,
+        to: usize,
+        input: usize,
+    ) -> Result<(), SimError> {
+        let connection = (from, output, to, input);
+        let index = self
+            .connections
+            .iter()
+            .position(|other| *other == connection)
+            .ok_or(SimError::UnknownConnection(connection))?;
+
+        self.connections.remove(index);
+        self.gates[from].fanout[output].retain(|target| *target != (to, input));
+        self.gates[to].drivers[input].retain(|driver| *driver != (from, output));
+        self.input_events.push((to, input));
+        Ok(())
+    }
+
+    /// Bits of a single input, inputs wider than one bit carry a bus.
+    pub fn get_input(&self, id: usize, input: usize) -> Result<&[Logic], SimError> {
+        self.check_input(id, input)?;
+        Ok(self.gates[id].inputs.pin(input))
+    }
+
+    /// Bits of a single output, outputs wider than one bit carry a bus.
+    pub fn get_output(&self, id: usize, output: usize) -> Result<&[Logic], SimError> {
+        self.check_output(id, output)?;
+        Ok(self.gates[id].outputs.pin(output))
+    }
+
+    /// Outputs connected to the input, as gate id and output index.
+    pub fn get_drivers(&self, id: usize, input: usize) -> Result<&[(usize, usize)], SimError> {
+        self.check_input(id, input)?;
+        Ok(&self.gates[id].drivers[input])
+    }
+
+    /// Whether drivers of the input disagree, only possible with
+    /// [`NetMode::Contention`].
+    pub fn is_conflicted(&self, id: usize, input: usize) -> Result<bool, SimError> {
+        self.check_input(id, input)?;
+        Ok(self.gates[id].conflicts[input])
+    }
+
+    pub fn net_mode(&self) -> NetMode {
+        self.net_mode
+    }
+
+    /// Changes how inputs with several drivers are resolved, their values
+    /// are updated on the next [`LogicSimulation::simulate`]. Switching to
+    /// [`NetMode::Exclusive`] fails while any input has several drivers.
+    pub fn set_net_mode(&mut self, net_mode: NetMode) -> Result<(), SimError> {
+        let driven_inputs: Vec<_> = self
+            .gates
+            .iter()
+            .flat_map(|(id, gate)| {
+                gate.drivers
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, drivers)| !drivers.is_empty())
+                    .map(move |(input, drivers)| (id, input, drivers.len()))
+            })
+            .collect();
+
+        if net_mode == NetMode::Exclusive {
+            if let Some((gate, input, _)) = driven_inputs.iter().find(|(.., count)| *count > 1) {
+                return Err(SimError::MultipleDrivers {
+                    gate: *gate,
+                    input: *input,
                 });
+            }
         }
+
+        self.input_events
+            .extend(driven_inputs.into_iter().map(|(id, input, _)| (id, input)));
+        self.net_mode = net_mode;
+        Ok(())
     }
 
-    pub fn add_connection(&mut self, from: usize, output: usize, to: usize, input: usize) {
-        self.connections.push((from, output, to, input));
+    /// Widths of all inputs and all outputs of the gate.
+    pub fn get_pin_widths(&self, id: usize) -> Result<(Vec<usize>, Vec<usize>), SimError> {
+        let gate = self.gate(id)?;
+        Ok((gate.inputs.widths(), gate.outputs.widths()))
     }
 
-    pub fn remove_connection(&mut self, from: usize, output: usize, to: usize, input: usize) {
-        self.connections
-            .retain(|connection| *connection != (from, output, to, input))
+    pub fn get_gate_name(&self, id: usize) -> Result<&str, SimError> {
+        Ok(&self.gate(id)?.name)
+    }
+
+    pub fn get_gate_kind(&self, id: usize) -> Result<&'static str, SimError> {
+        Ok(self.gate(id)?.kind)
+    }
+
+    /// Parameters of the gate, including its `delay` when it differs from
+    /// the delay of the gate type.
+    pub fn get_gate_params(&self, id: usize) -> Result<Params, SimError> {
+        let gate = self.gate(id)?;
+        let params = gate.component.params();
+        Ok(match gate.delay != gate.component.delay() {
+            true => params.with("delay", gate.delay),
+            false => params,
+        })
+    }
+
+    pub fn get_gate_delay(&self, id: usize) -> Result<u32, SimError> {
+        Ok(self.gate(id)?.delay)
+    }
+
+    /// Sets the propagation delay of a single gate, it is at least one tick.
+    /// Output changes already scheduled keep their time.
+    pub fn set_gate_delay(&mut self, id: usize, delay: u32) -> Result<(), SimError> {
+        self.gate_mut(id)?.delay = delay.max(1);
+        Ok(())
+    }
+
+    /// Number of ticks simulated so far.
+    pub fn time(&self) -> u64 {
+        self.time
+    }
+
+    /// Overrides the output of a gate, connected inputs will read the value
+    /// on the next [`LogicSimulation::simulate`], which then updates the gate
+    /// and its outputs again. Lets a sub-circuit drive its inner inputs.
+    pub fn set_output(
+        &mut self,
+        id: usize,
+        output: usize,
+        value: &[Logic],
+    ) -> Result<(), SimError> {
+        self.check_output(id, output)?;
+
+        let gate = &mut self.gates[id];
+        let bits = gate.outputs.pin_mut(output);
+        if bits.len() != value.len() {
+            return Err(SimError::WidthMismatch {
+                output: bits.len(),
+                input: value.len(),
+            });
+        }
+
+        gate.projected.pin_mut(output).copy_from_slice(value);
+        let bits = gate.outputs.pin_mut(output);
+        if bits != value {
+            bits.copy_from_slice(value);
+            self.input_events.extend_from_slice(&gate.fanout[output]);
+        }
+        Ok(())
+    }
+
+    /// Forwards a press to the gate, only sources react to it.
+    pub fn press(&mut self, id: usize) -> Result<(), SimError> {
+        self.gate_mut(id)?.component.press();
+        Ok(())
+    }
+
+    /// Forwards a release to the gate, only sources react to it.
+    pub fn release(&mut self, id: usize) -> Result<(), SimError> {
+        self.gate_mut(id)?.component.release();
+        Ok(())
     }
 
-    pub fn get_gate_state(&self, id: usize) -> (&[bool], &[bool]) {
-        let gate = self.gates.get(&id).unwrap();
-        (&gate.inputs, &gate.outputs)
+    /// Words of the gate's memory, empty for gates without one.
+    pub fn memory(&self, id: usize) -> Result<&[u64], SimError> {
+        Ok(self.gate(id)?.component.memory())
     }
 
-    pub fn get_gate_name(&self, id: usize) -> &'static str {
-        self.gates.get(&id).unwrap().name
+    /// Overwrites a word of the gate's memory, only memories react to it.
+    /// The gate updates its outputs with the next tick.
+    pub fn write_memory(&mut self, id: usize, address: usize, value: u64) -> Result<(), SimError> {
+        self.gate_mut(id)?.component.write_memory(address, value);
+        self.schedule(id);
+        Ok(())
     }
 
+    fn schedule(&mut self, id: usize) {
+        let gate = &mut self.gates[id];
+        if !gate.scheduled && !gate.stateful {
+            gate.scheduled = true;
+            self.scheduled.push(id);
+        }
+    }
+
+    /// Advances the simulation by one tick. Connections are checked when they
+    /// are made, so simulating can not fail.
     pub fn simulate(&mut self) {
-        // set all gates' inputs to false, we always propagate output state to
-        // input state for all gates below, and this way we can check if
-        // something changed the input
-        for (_, state) in &mut self.gates {
-            for input in state.inputs.iter_mut() {
-                *input = false;
+        // inputs read the outputs from the previous tick, combined
+        // according to the net mode
+        for (id, input) in std::mem::take(&mut self.input_events) {
+            let Some(gate) = self.gates.get(id) else {
+                // the gate was removed since the event was made
+                continue;
+            };
+            if input >= gate.inputs.len() {
+                // the id was reused by a gate with less inputs
+                continue;
+            }
+
+            // bits of buses are combined one by one
+            let drivers = &gate.drivers[input];
+            let mut value = std::mem::take(&mut self.scratch);
+            value.clear();
+            value.resize(gate.inputs.width(input), Logic::Z);
+            let mut conflicted = false;
+            for (driver_id, output) in drivers {
+                let driver = self.gates[*driver_id].outputs.pin(*output);
+                for (bit, driver_bit) in value.iter_mut().zip(driver) {
+                    let (combined, disagrees) = self.net_mode.combine(*bit, *driver_bit);
+                    *bit = combined;
+                    conflicted |= disagrees;
+                }
+            }
+
+            let gate = &mut self.gates[id];
+            gate.conflicts[input] = conflicted;
+            let bits = gate.inputs.pin_mut(input);
+            if *bits != *value {
+                bits.copy_from_slice(&value);
+                self.schedule(id);
             }
+            self.scratch = value;
         }
 
-        for (from, output, to, input) in &self.connections {
-            let output_state = self.gates.get(from).unwrap().outputs[*output];
-            let input_state = &mut self.gates.get_mut(to).unwrap().inputs[*input];
-            if *input_state {
-                // input was already set to true, do not allow it to be switched off
-                continue;
+        let mut scheduled = std::mem::take(&mut self.scheduled);
+        scheduled.extend_from_slice(&self.stateful);
+
+        let mut previous_outputs = Vec::new();
+        for id in &scheduled {
+            let gate = &mut self.gates[*id];
+            gate.scheduled = false;
+
+            previous_outputs.clear();
+            previous_outputs.extend_from_slice(&gate.projected.bits);
+
+            gate.update();
+
+            // a tick with one tick of delay applies its changes at its end
+            let time = self.time + gate.delay as u64 - 1;
+            for output in 0..gate.outputs.len() {
+                let range = gate.projected.offsets[output]..gate.projected.offsets[output + 1];
+                if gate.projected.bits[range.clone()] != previous_outputs[range] {
+                    let bits = gate.projected.pin(output).into();
+                    self.output_events
+                        .entry(time)
+                        .or_default()
+                        .push((*id, output, bits));
+                }
+            }
+        }
+
+        // keep the allocation for the next tick
+        scheduled.clear();
+        self.scheduled = scheduled;
+
+        self.changed.clear();
+        if let Some(events) = self.output_events.remove(&self.time) {
+            for (id, output, value) in events {
+                let gate = &mut self.gates[id];
+                let bits = gate.outputs.pin_mut(output);
+                if *bits != *value {
+                    bits.copy_from_slice(&value);
+                    self.input_events.extend_from_slice(&gate.fanout[output]);
+                    self.changed.push(id);
+                }
             }
-            *input_state = output_state;
         }
+        self.time += 1;
+
+        for (_, probe) in self.probes.iter_mut() {
+            let value = match probe.pin() {
+                ProbePin::Input { gate, input } => self.gates[gate].inputs.pin(input),
+                ProbePin::Output { gate, output } => self.gates[gate].outputs.pin(output),
+            };
+            probe.record(self.time, value);
+        }
+    }
+
+    /// Starts recording the values of the pin after every tick, returns the
+    /// id of the probe. Probes go away with their gate.
+    pub fn add_probe(&mut self, pin: ProbePin) -> Result<usize, SimError> {
+        let value = match pin {
+            ProbePin::Input { gate, input } => self.get_input(gate, input)?,
+            ProbePin::Output { gate, output } => self.get_output(gate, output)?,
+        };
+        let probe = Probe::new(pin, self.time, value);
+        Ok(self.probes.insert(probe))
+    }
 
-        for (_, state) in &mut self.gates {
+    pub fn remove_probe(&mut self, id: usize) -> Result<Probe, SimError> {
+        self.probes.remove(id).ok_or(SimError::UnknownProbe(id))
+    }
+
+    pub fn get_probe(&self, id: usize) -> Result<&Probe, SimError> {
+        self.probes.get(id).ok_or(SimError::UnknownProbe(id))
+    }
+
+    pub fn probes(&self) -> impl Iterator<Item = (usize, &Probe)> + '_ {
+        self.probes.iter()
+    }
+
+    /// Simulates until no output changes and no change is scheduled, for at
+    /// most `max_iterations` ticks. Returns the number of ticks during which
+    /// outputs still changed.
+    ///
+    /// Gates which kept changing during the second half of the ticks are
+    /// reported when the circuit does not settle, see [`Oscillation`].
+    pub fn settle(&mut self, max_iterations: usize) -> Result<usize, Oscillation> {
+        let mut active = vec![false; self.gates.iter().map(|(id, _)| id + 1).max().unwrap_or(0)];
+
+        for tick in 0..max_iterations {
+            self.simulate();
+
+            if self.changed.is_empty()
+                && self.input_events.is_empty()
+                && self.output_events.is_empty()
+            {
+                return Ok(tick);
+            }
+
+            if tick >= max_iterations / 2 {
+                for id in &self.changed {
+                    active[*id] = true;
+                }
+            }
+        }
+
+        Err(Oscillation {
+            cycle: self.find_cycle(&active),
+        })
+    }
+
+    /// Feedback loop through active gates in signal order, or the active
+    /// stateful gates when there is no such loop.
+    fn find_cycle(&self, active: &[bool]) -> Vec<usize> {
+        #[derive(Clone, Copy, PartialEq)]
+        enum Visit {
+            New,
+            OnPath,
+            Done,
+        }
+
+        fn visit(
+            sim: &LogicSimulation,
+            id: usize,
+            active: &[bool],
+            visits: &mut [Visit],
+            path: &mut Vec<usize>,
+        ) -> Option<Vec<usize>> {
+            visits[id] = Visit::OnPath;
+            path.push(id);
+
+            let targets = sim.gates[id].fanout.iter().flatten();
+            for (target, _) in targets {
+                if !active[*target] {
+                    continue;
+                }
+                match visits[*target] {
+                    Visit::OnPath => {
+                        let start = path.iter().position(|id| id == target).unwrap();
+                        return Some(path[start..].to_vec());
+                    }
+                    Visit::New => {
+                        if let Some(cycle) = visit(sim, *target, active, visits, path) {
+                            return Some(cycle);
+                        }
+                    }
+                    Visit::Done => {}
+                }
+            }
+
+            path.pop();
+            visits[id] = Visit::Done;
+            None
+        }
+
+        let mut visits = vec![Visit::New; active.len()];
+        let mut path = Vec::new();
+        for id in 0..active.len() {
+            if active[id] && visits[id] == Visit::New {
+                if let Some(cycle) = visit(self, id, active, &mut visits, &mut path) {
+                    return cycle;
+                }
+            }
+        }
+
+        self.stateful
+            .iter()
+            .copied()
+            .filter(|id| active[*id])
+            .collect()
+    }
+}
+
+/// A circuit which did not settle, because some of its gates keep changing.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Oscillation {
+    /// Gates of a feedback loop which keeps changing, in signal order. When
+    /// the changes come from stateful gates instead, like a clock, there is
+    /// no loop and these are the stateful gates.
+    pub cycle: Vec<usize>,
+}
+
+impl fmt::Display for Oscillation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let gates: Vec<_> = self.cycle.iter().map(|id| id.to_string()).collect();
+        write!(f, "circuit oscillates through gates {}", gates.join(", "))
+    }
+}
+
+impl std::error::Error for Oscillation {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gates::*;
+
+    const Y: Logic = Logic::One;
+    const N: Logic = Logic::Zero;
+    const X: Logic = Logic::X;
+    const Z: Logic = Logic::Z;
+
+    /// Full sweep over all gates and connections every tick, the event driven
+    /// simulation has to give the same results.
+    fn simulate_sweep(sim: &mut LogicSimulation) {
+        for (_, state) in sim.gates.iter_mut() {
+            state.inputs.bits.fill(Z);
+        }
+
+        for (from, output, to, input) in &sim.connections {
+            let output_state = sim.gates[*from].outputs.pin(*output).to_vec();
+            let input_state = sim.gates[*to].inputs.pin_mut(*input);
+            for (bit, driven) in input_state.iter_mut().zip(output_state) {
+                *bit = match (*bit, driven) {
+                    (bit, Z) => bit,
+                    (Z, driven) => driven,
+                    (bit, driven) => bit | driven,
+                };
+            }
+        }
+
+        for (_, state) in sim.gates.iter_mut() {
             state.update();
+            state.outputs = state.projected.clone();
         }
     }
+
+    /// Ring oscillator, clock driven half adder and a switch which is pressed
+    /// along the way.
+    fn circuit() -> (LogicSimulation, usize, usize) {
+        let mut sim = LogicSimulation::new();
+
+        let not_a = sim.add_gate(Not);
+        let not_b = sim.add_gate(Not);
+        let not_c = sim.add_gate(Not);
+        sim.add_connection(not_a, 0, not_b, 0).unwrap();
+        sim.add_connection(not_b, 0, not_c, 0).unwrap();
+        sim.add_connection(not_c, 0, not_a, 0).unwrap();
+
+        let clock = sim.add_source(Clock::new(5, 0.4));
+        let switch = sim.add_source(Switch::default());
+        let xor = sim.add_gate(Xor);
+        let and = sim.add_gate(And);
+        let nor = sim.add_gate(Nor);
+        sim.add_connection(clock, 0, xor, 0).unwrap();
+        sim.add_connection(switch, 0, xor, 1).unwrap();
+        sim.add_connection(clock, 0, and, 0).unwrap();
+        sim.add_connection(switch, 0, and, 1).unwrap();
+        sim.add_connection(xor, 0, nor, 0).unwrap();
+        sim.add_connection(and, 0, nor, 1).unwrap();
+        // second driver of the same input
+        sim.add_connection(not_a, 0, nor, 1).unwrap();
+
+        (sim, switch, and)
+    }
+
+    fn snapshot(sim: &LogicSimulation) -> Vec<(usize, Vec<Logic>, Vec<Logic>)> {
+        sim.gates
+            .iter()
+            .map(|(id, gate)| (id, gate.inputs.bits.to_vec(), gate.outputs.bits.to_vec()))
+            .collect()
+    }
+
+    #[test]
+    fn matches_full_sweep() {
+        let (mut events, switch, and) = circuit();
+        let (mut sweep, _, _) = circuit();
+
+        for tick in 0..40 {
+            if tick % 7 == 0 {
+                events.press(switch).unwrap();
+                sweep.press(switch).unwrap();
+            }
+
+            if tick == 20 {
+                events.remove_connection(switch, 0, and, 1).unwrap();
+                sweep.remove_connection(switch, 0, and, 1).unwrap();
+            }
+
+            if tick == 30 {
+                events.remove_gate(0).unwrap();
+                sweep.remove_gate(0).unwrap();
+            }
+
+            events.simulate();
+            simulate_sweep(&mut sweep);
+
+            assert_eq!(snapshot(&events), snapshot(&sweep), "tick {tick}");
+        }
+    }
+
+    #[test]
+    fn errors() {
+        let mut sim = LogicSimulation::new();
+        let not = sim.add_gate(Not);
+        let and = sim.add_gate(And);
+
+        assert_eq!(
+            sim.add_connection(not, 1, and, 0),
+            Err(SimError::OutputOutOfRange {
+                gate: not,
+                output: 1
+            })
+        );
+        assert_eq!(
+            sim.add_connection(not, 0, and, 2),
+            Err(SimError::InputOutOfRange {
+                gate: and,
+                input: 2
+            })
+        );
+        assert_eq!(
+            sim.add_connection(not, 0, 7, 0),
+            Err(SimError::UnknownGate(7))
+        );
+
+        sim.add_connection(not, 0, and, 0).unwrap();
+        assert_eq!(
+            sim.add_connection(not, 0, and, 0),
+            Err(SimError::DuplicateConnection((not, 0, and, 0)))
+        );
+
+        assert_eq!(
+            sim.remove_connection(not, 0, and, 1),
+            Err(SimError::UnknownConnection((not, 0, and, 1)))
+        );
+        sim.remove_connection(not, 0, and, 0).unwrap();
+
+        sim.remove_gate(not).unwrap();
+        assert_eq!(sim.remove_gate(not), Err(SimError::UnknownGate(not)));
+        assert_eq!(sim.get_gate_name(not), Err(SimError::UnknownGate(not)));
+        assert_eq!(sim.press(not), Err(SimError::UnknownGate(not)));
+        assert_eq!(
+            sim.set_output(and, 1, &[Y]),
+            Err(SimError::OutputOutOfRange {
+                gate: and,
+                output: 1
+            })
+        );
+
+        // inputs of the and gate float
+        sim.simulate();
+        assert_eq!(sim.get_output(and, 0).unwrap(), [X]);
+    }
+
+    #[test]
+    fn removed_ids_are_reused() {
+        let mut sim = LogicSimulation::new();
+        let and = sim.add_gate(And);
+        let not = sim.add_gate(Not);
+        sim.add_connection(not, 0, and, 1).unwrap();
+
+        // both gates and the input of the and gate have pending events
+        sim.remove_gate(and).unwrap();
+        sim.remove_gate(not).unwrap();
+        assert_eq!(sim.add_gate(Yes), not);
+        assert_eq!(sim.add_gate(Not), and);
+
+        sim.simulate();
+        assert_eq!(sim.get_input(and, 0).unwrap(), [Z]);
+        assert_eq!(sim.get_output(and, 0).unwrap(), [X]);
+        assert_eq!(sim.get_input(not, 0).unwrap(), [Z]);
+        assert_eq!(sim.get_output(not, 0).unwrap(), [X]);
+    }
+
+    #[test]
+    fn settled_circuit_updates_nothing() {
+        let mut sim = LogicSimulation::new();
+        let mut previous = sim.add_source(Switch::new(true));
+        for _ in 0..100 {
+            let next = sim.add_gate(Yes);
+            sim.add_connection(previous, 0, next, 0).unwrap();
+            previous = next;
+        }
+
+        for _ in 0..101 {
+            sim.simulate();
+        }
+        assert_eq!(sim.get_output(previous, 0).unwrap(), [Y]);
+
+        sim.simulate();
+        assert!(sim.scheduled.is_empty());
+        assert!(sim.input_events.is_empty());
+    }
+
+    #[test]
+    fn net_modes() {
+        let mut sim = LogicSimulation::new();
+        let on = sim.add_source(Switch::new(true));
+        let off = sim.add_source(Switch::new(false));
+        let led = sim.add_gate(Led);
+        sim.add_connection(on, 0, led, 0).unwrap();
+        sim.add_connection(off, 0, led, 0).unwrap();
+        // let the switches set their outputs
+        sim.simulate();
+
+        for (mode, value, conflicted) in [
+            (NetMode::WiredOr, Y, false),
+            (NetMode::WiredAnd, N, false),
+            (NetMode::Contention, X, true),
+        ] {
+            sim.set_net_mode(mode).unwrap();
+            sim.simulate();
+            assert_eq!(sim.get_input(led, 0).unwrap(), [value], "{mode}");
+            assert_eq!(sim.is_conflicted(led, 0), Ok(conflicted), "{mode}");
+        }
+
+        assert_eq!(
+            sim.set_net_mode(NetMode::Exclusive),
+            Err(SimError::MultipleDrivers {
+                gate: led,
+                input: 0
+            })
+        );
+        assert_eq!(sim.net_mode(), NetMode::Contention);
+
+        // a single driver never conflicts
+        sim.remove_connection(off, 0, led, 0).unwrap();
+        sim.set_net_mode(NetMode::Exclusive).unwrap();
+        sim.simulate();
+        assert_eq!(sim.get_input(led, 0).unwrap(), [Y]);
+        assert_eq!(sim.is_conflicted(led, 0), Ok(false));
+        assert_eq!(
+            sim.add_connection(off, 0, led, 0),
+            Err(SimError::MultipleDrivers {
+                gate: led,
+                input: 0
+            })
+        );
+    }
+
+    #[test]
+    fn buses() {
+        let mut sim = LogicSimulation::new();
+        let switches: Vec<_> = [true, false, true, true]
+            .into_iter()
+            .map(|on| sim.add_source(Switch::new(on)))
+            .collect();
+        let merger = sim.add_boxed_gate(BoxedGate::dynamic(Merger::new(4)));
+        let splitter = sim.add_boxed_gate(BoxedGate::dynamic(Splitter::new(4)));
+        let not = sim.add_gate(Not);
+        for (bit, switch) in switches.iter().enumerate() {
+            sim.add_connection(*switch, 0, merger, bit).unwrap();
+        }
+        sim.add_connection(merger, 0, splitter, 0).unwrap();
+        sim.add_connection(splitter, 1, not, 0).unwrap();
+
+        assert_eq!(
+            sim.add_connection(merger, 0, not, 0),
+            Err(SimError::WidthMismatch {
+                output: 4,
+                input: 1
+            })
+        );
+        assert_eq!(sim.get_pin_widths(splitter), Ok((vec![4], vec![1; 4])));
+
+        for _ in 0..4 {
+            sim.simulate();
+        }
+        assert_eq!(sim.get_output(merger, 0).unwrap(), [Y, N, Y, Y]);
+        assert_eq!(sim.get_output(splitter, 2).unwrap(), [Y]);
+        assert_eq!(sim.get_output(not, 0).unwrap(), [Y]);
+    }
+
+    #[test]
+    fn tri_state_bus() {
+        let mut sim = LogicSimulation::new();
+        sim.set_net_mode(NetMode::Contention).unwrap();
+        let led = sim.add_gate(Led);
+
+        let [data_a, enable_a, data_b, enable_b] =
+            [true, false, false, false].map(|on| sim.add_source(Switch::new(on)));
+        for (data, enable) in [(data_a, enable_a), (data_b, enable_b)] {
+            let buffer = sim.add_gate(TriState);
+            sim.add_connection(data, 0, buffer, 0).unwrap();
+            sim.add_connection(enable, 0, buffer, 1).unwrap();
+            sim.add_connection(buffer, 0, led, 0).unwrap();
+        }
+
+        let mut expect = |press: &[usize], value, conflicted| {
+            for id in press {
+                sim.press(*id).unwrap();
+            }
+            for _ in 0..3 {
+                sim.simulate();
+            }
+            assert_eq!(sim.get_input(led, 0).unwrap(), [value]);
+            assert_eq!(sim.is_conflicted(led, 0), Ok(conflicted));
+        };
+
+        // released by both buffers, then driven by one at a time
+        expect(&[], Z, false);
+        expect(&[enable_a], Y, false);
+        expect(&[enable_a, enable_b], N, false);
+        expect(&[enable_a], X, true);
+    }
+
+    #[test]
+    fn delays() {
+        let mut sim = LogicSimulation::new();
+        let switch = sim.add_source(Switch::new(false));
+        let slow = sim.add_gate(Not);
+        let fast = sim.add_gate(Not);
+        sim.add_connection(switch, 0, slow, 0).unwrap();
+        sim.add_connection(switch, 0, fast, 0).unwrap();
+        sim.set_gate_delay(slow, 3).unwrap();
+        assert_eq!(sim.get_gate_params(slow).unwrap().get("delay"), Some("3"));
+        assert_eq!(sim.get_gate_params(fast).unwrap().get("delay"), None);
+
+        for _ in 0..4 {
+            sim.simulate();
+        }
+        sim.press(switch).unwrap();
+
+        // the switch changes at the end of the first tick, the gates read it
+        // in the second one
+        let mut outputs = Vec::new();
+        for _ in 0..5 {
+            sim.simulate();
+            outputs.push([slow, fast].map(|id| sim.get_output(id, 0).unwrap()[0]));
+        }
+        assert_eq!(outputs, [[Y, Y], [Y, N], [Y, N], [N, N], [N, N]]);
+        assert_eq!(sim.time(), 9);
+    }
+
+    #[test]
+    fn probes() {
+        let mut sim = LogicSimulation::new();
+        let switch = sim.add_source(Switch::new(false));
+        let not = sim.add_gate(Not);
+        sim.add_connection(switch, 0, not, 0).unwrap();
+        let input = sim
+            .add_probe(ProbePin::Input {
+                gate: not,
+                input: 0,
+            })
+            .unwrap();
+        let output = sim
+            .add_probe(ProbePin::Output {
+                gate: not,
+                output: 0,
+            })
+            .unwrap();
+
+        for _ in 0..4 {
+            sim.simulate();
+        }
+        sim.press(switch).unwrap();
+        for _ in 0..4 {
+            sim.simulate();
+        }
+
+        // values hold from the end of the tick which changed them
+        let changes = |id| {
+            let probe: &Probe = sim.get_probe(id).unwrap();
+            probe
+                .changes()
+                .map(|(time, value)| (time, value[0]))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(changes(input), [(0, Z), (1, X), (2, N), (6, Y)]);
+        assert_eq!(changes(output), [(0, X), (2, Y), (6, N)]);
+
+        assert_eq!(
+            sim.add_probe(ProbePin::Input {
+                gate: not,
+                input: 1
+            })
+            .err(),
+            Some(SimError::InputOutOfRange {
+                gate: not,
+                input: 1
+            })
+        );
+        sim.remove_probe(input).unwrap();
+        sim.remove_gate(not).unwrap();
+        assert_eq!(sim.probes().count(), 0);
+        assert!(sim.remove_probe(output).is_err());
+    }
+
+    #[test]
+    fn memory() {
+        // the RAM reads address zero, which is written from outside
+        let mut sim = LogicSimulation::new();
+        let switch = sim.add_source(Switch::new(false));
+        let ram = sim.add_boxed_gate(BoxedGate::dynamic(crate::memory::Ram::new(1, 2)));
+        sim.add_connection(switch, 0, ram, 0).unwrap();
+        sim.settle(10).unwrap();
+        assert_eq!(sim.get_output(ram, 0).unwrap(), [N, N]);
+
+        sim.write_memory(ram, 0, 2).unwrap();
+        sim.settle(10).unwrap();
+        assert_eq!(sim.get_output(ram, 0).unwrap(), [N, Y]);
+        assert_eq!(sim.memory(ram).unwrap(), [2, 0]);
+
+        // gates without memory ignore writes
+        sim.write_memory(switch, 0, 1).unwrap();
+        assert!(sim.memory(switch).unwrap().is_empty());
+        assert!(sim.memory(7).is_err());
+    }
+
+    #[test]
+    fn hazard() {
+        // a AND NOT a is always low in theory, but the slower inverter lets
+        // a rising edge through for as many ticks as its delay
+        let mut sim = LogicSimulation::new();
+        let switch = sim.add_source(Switch::new(false));
+        let not = sim.add_gate(Not);
+        let and = sim.add_gate(And);
+        sim.add_connection(switch, 0, not, 0).unwrap();
+        sim.add_connection(switch, 0, and, 0).unwrap();
+        sim.add_connection(not, 0, and, 1).unwrap();
+        sim.set_gate_delay(not, 2).unwrap();
+
+        for _ in 0..6 {
+            sim.simulate();
+        }
+        sim.press(switch).unwrap();
+
+        let mut outputs = Vec::new();
+        for _ in 0..6 {
+            sim.simulate();
+            outputs.push(sim.get_output(and, 0).unwrap()[0]);
+        }
+        assert_eq!(outputs, [N, Y, Y, N, N, N]);
+    }
+
+    #[test]
+    fn settle() {
+        // NAND with two inverters oscillates once enabled
+        let mut sim = LogicSimulation::new();
+        let enable = sim.add_source(Switch::new(false));
+        let nand = sim.add_gate(Nand);
+        let not_a = sim.add_gate(Not);
+        let not_b = sim.add_gate(Not);
+        sim.add_connection(enable, 0, nand, 0).unwrap();
+        sim.add_connection(nand, 0, not_a, 0).unwrap();
+        sim.add_connection(not_a, 0, not_b, 0).unwrap();
+        sim.add_connection(not_b, 0, nand, 1).unwrap();
+        sim.set_gate_delay(not_b, 4).unwrap();
+
+        // the low enable passes the loop once, taking a tick for the switch
+        // and every gate except the slower inverter which takes 4
+        assert_eq!(sim.settle(100), Ok(7));
+        assert_eq!(sim.get_output(not_b, 0).unwrap(), [Y]);
+        assert_eq!(sim.settle(100), Ok(0));
+
+        sim.press(enable).unwrap();
+        assert_eq!(
+            sim.settle(100),
+            Err(Oscillation {
+                cycle: vec![nand, not_a, not_b]
+            })
+        );
+
+        // a clock never settles, but there is no loop
+        let mut sim = LogicSimulation::new();
+        let clock = sim.add_source(Clock::default());
+        let not = sim.add_gate(Not);
+        sim.add_connection(clock, 0, not, 0).unwrap();
+        assert_eq!(sim.settle(10), Err(Oscillation { cycle: vec![clock] }));
+    }
 }
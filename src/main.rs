@@ -1,15 +1,24 @@
-use gates::*;
+use logic_sim::{
+    circuit_file::Circuit,
+    cli, component,
+    expression::{self, Expr},
+    gates::*,
+    logic::Logic,
+    logic_simulation::NetMode,
+    memory,
+    probe::{Probe, ProbePin},
+    registry::{GateRegistry, Shape},
+    synthesis,
+    truth_table::{self, TruthTable},
+};
 use macroquad::{
     hash,
     prelude::*,
-    ui::{root_ui, Skin},
+    ui::{root_ui, widgets, Skin},
 };
 
 use crate::board::BoardSimulation;
 
-mod gates;
-mod logic_simulation;
-
 fn is_point_inside_box(
     (point_x, point_y): (f32, f32),
     (box_x, box_y, box_w, box_h): (f32, f32, f32, f32),
@@ -21,35 +30,62 @@ enum GateMouseHover {
     Input(usize, Vec2),
     Output(usize, Vec2),
     Gate(Vec2),
+    Control,
 }
 
-fn draw_gate(
-    name: &str,
-    x: f32,
-    y: f32,
-    inputs: &[bool],
-    outputs: &[bool],
-) -> Option<GateMouseHover> {
-    let max_io_len = usize::max(inputs.len(), outputs.len()) as f32;
-    let io_h = 20f32;
-    let io_w = 20f32;
-    let io_spacing = 5f32;
-    let h = max_io_len * io_h + max_io_len * io_spacing + io_spacing;
-    let w = h;
+/// Color of a pin or a wire: unknown when any bit is unknown, lit when any
+/// bit is high and floating when no bit is driven.
+fn signal_color(bits: &[Logic], low: Color) -> Color {
+    if bits.contains(&Logic::X) {
+        PURPLE
+    } else if bits.contains(&Logic::One) {
+        RED
+    } else if !bits.is_empty() && bits.iter().all(|bit| *bit == Logic::Z) {
+        DARKBLUE
+    } else {
+        low
+    }
+}
 
-    let (font_size, font_scale, font_aspect) = camera_font_scale(h / 2.);
-    let text_params = TextParams {
-        font_size,
-        font_scale,
-        font_scale_aspect: font_aspect,
-        color: BLACK,
-        ..Default::default()
-    };
+/// Hexadecimal value of a bus, digits with an unknown or floating bit are
+/// shown as `X` or `Z`.
+fn bus_label(bits: &[Logic]) -> String {
+    bits.chunks(4)
+        .rev()
+        .map(|digit| {
+            if digit.contains(&Logic::X) {
+                'X'
+            } else if digit.contains(&Logic::Z) {
+                'Z'
+            } else {
+                let value = digit
+                    .iter()
+                    .rev()
+                    .fold(0, |value, bit| (value << 1) | bit.is_high() as u32);
+                char::from_digit(value, 16).unwrap()
+            }
+        })
+        .collect()
+}
 
-    let text_dimensions = measure_text(name, None, font_size, font_scale);
+/// Draws a single pin, pins carrying a bus get an outline and their value in
+/// hexadecimal next to them.
+fn draw_pin((x, y, w, h): (f32, f32, f32, f32), bits: &[Logic], label_x: f32) {
+    draw_rectangle(x, y, w, h, signal_color(bits, GRAY));
 
-    let whitish = Color::from_rgba(0xcc, 0xcc, 0xcc, 0xff);
-    draw_rectangle(x, y, w, h, whitish);
+    if bits.len() > 1 {
+        draw_rectangle_lines(x, y, w, h, 3f32, SKYBLUE);
+        draw_text(&bus_label(bits), label_x, y, h * 0.8, SKYBLUE);
+    }
+}
+
+fn draw_pins(
+    (x, y, w, h): (f32, f32, f32, f32),
+    inputs: &[&[Logic]],
+    outputs: &[&[Logic]],
+) -> Option<GateMouseHover> {
+    let io_h = 20f32;
+    let io_w = 20f32;
 
     let mouse_pos = mouse_position();
     let mut mouse_hover = None;
@@ -59,7 +95,7 @@ fn draw_gate(
         let t = 0.5 * dt + index as f32 * dt;
         let in_x = x - io_w / 2.;
         let in_y = y + t - (io_h / 2.);
-        draw_rectangle(in_x, in_y, io_w, io_h, if *state { RED } else { GRAY });
+        draw_pin((in_x, in_y, io_w, io_h), state, in_x - io_w * 1.5);
 
         if is_point_inside_box(mouse_pos, (in_x, in_y, io_w, io_h)) {
             mouse_hover = Some(GateMouseHover::Input(index, (x, in_y + io_h / 2.).into()));
@@ -72,7 +108,7 @@ fn draw_gate(
         let t = 0.5 * dt + index as f32 * dt;
         let out_x = x + w - io_w / 2.;
         let out_y = y + t - (io_h / 2.);
-        draw_rectangle(out_x, out_y, io_w, io_h, if *state { RED } else { GRAY });
+        draw_pin((out_x, out_y, io_w, io_h), state, out_x + io_w * 1.2);
 
         if is_point_inside_box(mouse_pos, (out_x, out_y, io_w, io_h)) {
             mouse_hover = Some(GateMouseHover::Output(
@@ -83,112 +119,638 @@ fn draw_gate(
         }
     }
 
+    if mouse_hover.is_some() {
+        mouse_hover
+    } else if is_point_inside_box(mouse_pos, (x, y, w, h)) {
+        Some(GateMouseHover::Gate(mouse_pos.into()))
+    } else {
+        None
+    }
+}
+
+fn draw_label(name: &str, (x, y, w, h): (f32, f32, f32, f32), size: f32) {
+    let (font_size, font_scale, font_aspect) = camera_font_scale(size);
+    let text_params = TextParams {
+        font_size,
+        font_scale,
+        font_scale_aspect: font_aspect,
+        color: BLACK,
+        ..Default::default()
+    };
+
+    let text_dimensions = measure_text(name, None, font_size, font_scale);
+
     draw_text_ex(
         name,
         x + (w - text_dimensions.width) / 2.,
         y + (h - text_dimensions.height) / 2. + text_dimensions.offset_y,
         text_params,
     );
+}
 
-    if mouse_hover.is_some() {
-        mouse_hover
-    } else if is_point_inside_box(mouse_pos, (x, y, w, h)) {
-        Some(GateMouseHover::Gate(mouse_pos.into()))
+fn gate_size(inputs: usize, outputs: usize) -> (f32, f32) {
+    let max_io_len = usize::max(inputs, outputs) as f32;
+    let io_h = 20f32;
+    let io_spacing = 5f32;
+    let h = max_io_len * io_h + max_io_len * io_spacing + io_spacing;
+    (h, h)
+}
+
+/// Size of the body of a gate of that shape, sources are wider and basic
+/// gates only grow taller with more inputs.
+fn body_size(shape: Shape, inputs: usize, outputs: usize) -> (f32, f32) {
+    match shape {
+        Shape::Source => {
+            let (w, h) = gate_size(1, outputs);
+            (w * 2., h)
+        }
+        Shape::Tall => (gate_size(2, outputs).0, gate_size(inputs, outputs).1),
+        _ => gate_size(inputs, outputs),
+    }
+}
+
+/// Offset of a pin from the position of its gate, where the `draw_*`
+/// functions put it for a gate of that shape.
+fn pin_offset(shape: Shape, inputs: usize, outputs: usize, pin: usize, is_output: bool) -> Vec2 {
+    let (w, h) = body_size(shape, inputs, outputs);
+    let (x, pins) = if is_output {
+        (w, outputs)
     } else {
-        None
+        (0., inputs)
+    };
+    Vec2::new(x, (pin as f32 + 0.5) * h / pins as f32)
+}
+
+fn draw_gate(
+    name: &str,
+    shape: Shape,
+    x: f32,
+    y: f32,
+    inputs: &[&[Logic]],
+    outputs: &[&[Logic]],
+) -> Option<GateMouseHover> {
+    let (w, h) = body_size(shape, inputs.len(), outputs.len());
+
+    let whitish = Color::from_rgba(0xcc, 0xcc, 0xcc, 0xff);
+    draw_rectangle(x, y, w, h, whitish);
+
+    let mouse_hover = draw_pins((x, y, w, h), inputs, outputs);
+
+    draw_label(name, (x, y, w, h), w.min(h) / 2.);
+
+    mouse_hover
+}
+
+fn draw_led(name: &str, x: f32, y: f32, inputs: &[&[Logic]]) -> Option<GateMouseHover> {
+    let (w, h) = gate_size(inputs.len(), 0);
+
+    let whitish = Color::from_rgba(0xcc, 0xcc, 0xcc, 0xff);
+    draw_rectangle(x, y, w, h, whitish);
+
+    let mouse_hover = draw_pins((x, y, w, h), inputs, &[]);
+
+    let bits: Vec<Logic> = inputs.iter().flat_map(|pin| pin.iter().copied()).collect();
+    draw_circle(
+        x + w / 2.,
+        y + h / 2.,
+        h / 3.,
+        signal_color(&bits, DARKGRAY),
+    );
+    draw_label(name, (x, y + h * 3. / 4., w, h / 4.), h / 6.);
+
+    mouse_hover
+}
+
+/// Draws segments in the order used by [`SevenSegment`] into the given box.
+fn draw_segments((x, y, w, h): (f32, f32, f32, f32), segments: &[Logic; 7]) {
+    let thickness = w / 8.;
+    let half = (h - thickness) / 2.;
+    #[rustfmt::skip]
+    let rects = [
+        (x, y, w, thickness),
+        (x + w - thickness, y, thickness, half + thickness),
+        (x + w - thickness, y + half, thickness, half + thickness),
+        (x, y + h - thickness, w, thickness),
+        (x, y + half, thickness, half + thickness),
+        (x, y, thickness, half + thickness),
+        (x, y + half, w, thickness),
+    ];
+
+    for ((x, y, w, h), segment) in rects.into_iter().zip(segments) {
+        let unlit = Color::from_rgba(0x40, 0x40, 0x40, 0xff);
+        draw_rectangle(x, y, w, h, signal_color(&[*segment], unlit));
+    }
+}
+
+fn draw_seven_segment(
+    x: f32,
+    y: f32,
+    inputs: &[&[Logic]],
+    segments: &[Logic; 7],
+) -> Option<GateMouseHover> {
+    let (w, h) = gate_size(inputs.len(), 0);
+    let w = w / 2.;
+
+    draw_rectangle(x, y, w, h, BLACK);
+
+    let mouse_hover = draw_pins((x, y, w, h), inputs, &[]);
+
+    draw_segments((x + w / 4., y + h / 8., w / 2., h * 3. / 4.), segments);
+
+    mouse_hover
+}
+
+/// Sources are drawn wider than gates, with a clickable control in the middle
+/// of the body which reflects the state of the first output.
+fn draw_source(name: &str, x: f32, y: f32, outputs: &[&[Logic]]) -> Option<GateMouseHover> {
+    let (w, h) = gate_size(1, outputs.len());
+    let w = w * 2.;
+
+    let whitish = Color::from_rgba(0xcc, 0xcc, 0xcc, 0xff);
+    draw_rectangle(x, y, w, h, whitish);
+
+    let mouse_hover = draw_pins((x, y, w, h), &[], outputs);
+
+    let control = (x + w / 4., y + h / 4., w / 2., h / 2.);
+    let bits = outputs.first().copied().unwrap_or_default();
+    draw_rectangle(
+        control.0,
+        control.1,
+        control.2,
+        control.3,
+        signal_color(bits, GRAY),
+    );
+    draw_label(name, (x, y, w, h / 4.), h / 5.);
+
+    match mouse_hover {
+        Some(GateMouseHover::Gate(_)) if is_point_inside_box(mouse_position(), control) => {
+            draw_rectangle_lines(control.0, control.1, control.2, control.3, 4f32, WHITE);
+            Some(GateMouseHover::Control)
+        }
+        mouse_hover => mouse_hover,
+    }
+}
+
+/// Draws the history of the probes as a timing diagram along the bottom of
+/// the screen, ending at the current tick with `zoom` pixels per tick. A
+/// cursor under the mouse shows the values at the tick it points at.
+fn draw_waveforms(probes: &[(String, &Probe)], time: u64, zoom: f32) {
+    if probes.is_empty() {
+        return;
+    }
+
+    let row_h = 24f32;
+    let label_w = 120f32;
+    let top = screen_height() - row_h * (probes.len() + 1) as f32;
+    draw_rectangle(0., top, screen_width(), screen_height() - top, BLACK);
+
+    let start = time.saturating_sub(((screen_width() - label_w) / zoom) as u64);
+    let x_of = |tick: u64| label_w + (tick - start) as f32 * zoom;
+
+    // tick labels at least 50 pixels apart, on multiples of 1, 2 or 5
+    let mut step = 1;
+    while (step as f32) * zoom < 50. {
+        step = match step.to_string().as_bytes()[0] {
+            b'2' => step / 2 * 5,
+            _ => step * 2,
+        };
+    }
+    for tick in (start.div_ceil(step) * step..=time).step_by(step as usize) {
+        draw_line(x_of(tick), top, x_of(tick), top + row_h / 4., 1., GRAY);
+        draw_text(
+            &tick.to_string(),
+            x_of(tick) + 2.,
+            top + row_h * 0.75,
+            16.,
+            GRAY,
+        );
+    }
+
+    for (row, (label, probe)) in probes.iter().enumerate() {
+        let y = top + row_h * (row + 1) as f32;
+        draw_text(label, 4., y + row_h * 0.7, 16., WHITE);
+
+        let (high, low, middle) = (y + 4., y + row_h - 4., y + row_h / 2.);
+        let mut changes = probe.changes().peekable();
+        while let Some((from, value)) = changes.next() {
+            let until = changes.peek().map_or(time, |(next, _)| *next);
+            if until < start {
+                continue;
+            }
+            let (x0, x1) = (x_of(from.max(start)), x_of(until));
+            let color = signal_color(value, LIGHTGRAY);
+
+            if value.len() > 1 {
+                draw_rectangle_lines(x0, high, x1 - x0, low - high, 2., color);
+                draw_text(&bus_label(value), x0 + 3., low - 3., 16., SKYBLUE);
+                continue;
+            }
+
+            let level = match value[0] {
+                Logic::One => high,
+                Logic::Zero => low,
+                Logic::X | Logic::Z => middle,
+            };
+            draw_line(x0, level, x1, level, 2., color);
+            if from >= start {
+                draw_line(x0, high, x0, low, 1., color);
+            }
+        }
+    }
+
+    let (mouse_x, mouse_y) = mouse_position();
+    if mouse_y >= top && mouse_x >= label_w {
+        let tick = start + ((mouse_x - label_w) / zoom) as u64;
+        if tick < time {
+            let x = x_of(tick);
+            draw_line(x, top, x, screen_height(), 1., YELLOW);
+            draw_text(
+                &format!("tick {tick}"),
+                x + 4.,
+                top + row_h * 0.75,
+                16.,
+                YELLOW,
+            );
+            for (row, (_, probe)) in probes.iter().enumerate() {
+                if let Some(value) = probe.value_at(tick) {
+                    let y = top + row_h * (row + 2) as f32;
+                    draw_text(&bus_label(value), x + 4., y - 4., 16., YELLOW);
+                }
+            }
+        }
     }
 }
 
 mod board {
     use std::collections::HashMap;
 
+    use logic_sim::{
+        arena::Arena,
+        circuit_file::{Circuit, ComponentDef, ConnectionEntry, GateEntry, LoadError, PinEntry},
+        component::{self, ComponentError},
+        expression::Expr,
+        extraction::{self, ExtractError},
+        gates::Params,
+        logic::Logic,
+        logic_simulation::{LogicSimulation, NetMode, Oscillation, SimError},
+        memory::Rom,
+        probe::{Probe, ProbePin},
+        registry::{GateRegistry, Shape},
+        truth_table, vcd,
+    };
     use macroquad::prelude::Vec2;
 
-    use crate::{gates::Gate, logic_simulation::LogicSimulation};
+    /// Every gate of the board exists in the simulation under the same id.
+    const IN_SYNC: &str = "board and simulation share gate ids";
+
+    /// Bits of every input and every output of a gate.
+    pub(crate) type PinValues<'a> = (Vec<&'a [Logic]>, Vec<&'a [Logic]>);
+
+    /// Gate id, pin id and offset of the pin from the gate position.
+    type PinAnchor = (usize, usize, Vec2);
+
+    /// Position of a connected pin along with its bits.
+    type PinState<'a> = (PinAnchor, &'a [Logic]);
 
     pub(crate) struct BoardSimulation {
         sim: LogicSimulation,
-        gates: HashMap<usize, Vec2>,
-        connections: Vec<((usize, usize, Vec2), (usize, usize, Vec2))>,
+        /// Positions of gates, ids are handed out in the same order as the
+        /// ids of the simulation, so they always match.
+        gates: Arena<Vec2>,
+        connections: Vec<(PinAnchor, PinAnchor)>,
+        components: Vec<ComponentDef>,
+        registry: GateRegistry,
+        /// Counts edits of gates and connections, views derived from the
+        /// circuit are stale once it changes.
+        revision: u64,
     }
 
     impl BoardSimulation {
         pub(crate) fn new() -> BoardSimulation {
             BoardSimulation {
                 sim: LogicSimulation::new(),
-                gates: HashMap::new(),
+                gates: Arena::new(),
                 connections: Vec::new(),
+                components: Vec::new(),
+                registry: GateRegistry::default(),
+                revision: 0,
             }
         }
 
-        pub(crate) fn add_gate<const INPUTS: usize, const OUTPUTS: usize>(
+        pub(crate) fn revision(&self) -> u64 {
+            self.revision
+        }
+
+        /// Gate kinds the board creates gates of.
+        pub(crate) fn registry(&self) -> &GateRegistry {
+            &self.registry
+        }
+
+        /// Adds a gate of a kind in the registry or a custom component.
+        pub(crate) fn add_kind(
             &mut self,
-            gate: impl Gate<INPUTS, OUTPUTS> + 'static,
+            kind: &str,
+            params: &Params,
             pos: Vec2,
-        ) {
-            let gate_id = self.sim.add_gate(gate);
-            self.gates.insert(gate_id, pos);
+        ) -> Result<(), LoadError> {
+            let boxed = component::create(kind, params, &self.registry, &self.components)?;
+            let gate_id = self.sim.add_boxed_gate(boxed);
+            self.insert_pos(gate_id, pos);
+            Ok(())
         }
 
-        pub(crate) fn remove_gate(&mut self, gate_id: usize) {
-            self.sim.remove_gate(gate_id);
-            if let Some(_) = self.gates.remove(&gate_id) {
-                self.connections
-                    .retain(|(output, input)| output.0 != gate_id && input.0 != gate_id);
+        /// Adds the gates and connections of a circuit laid out by
+        /// [`logic_sim::synthesis`] at `origin`, connections go to the pins of
+        /// the gates as they are drawn.
+        pub(crate) fn add_circuit(
+            &mut self,
+            circuit: &Circuit,
+            origin: Vec2,
+        ) -> Result<(), LoadError> {
+            let mut ids = HashMap::new();
+            for gate in &circuit.gates {
+                let boxed =
+                    component::create(&gate.kind, &gate.params, &self.registry, &self.components)?;
+                let gate_id = self.sim.add_boxed_gate(boxed);
+                self.insert_pos(gate_id, origin + Vec2::from(gate.pos));
+                ids.insert(gate.id, gate_id);
+            }
+
+            let anchor = |board: &BoardSimulation, (file_id, pin, _): PinEntry, is_output| {
+                let gate_id = *ids.get(&file_id).ok_or(LoadError::UnknownGate(file_id))?;
+                let shape = board.shape(gate_id);
+                let (inputs, outputs) = board.sim.get_pin_widths(gate_id).expect(IN_SYNC);
+                let offset = crate::pin_offset(shape, inputs.len(), outputs.len(), pin, is_output);
+                Ok((gate_id, pin, offset))
+            };
+            for ConnectionEntry { output, input } in &circuit.connections {
+                let output = anchor(self, *output, true)?;
+                let input = anchor(self, *input, false)?;
+                self.add_connection(input, output)
+                    .map_err(LoadError::InvalidConnection)?;
             }
+            Ok(())
+        }
+
+        /// Packages the given gates and connections between them as a new
+        /// component, see [`component::define`].
+        pub(crate) fn create_component(
+            &mut self,
+            name: &str,
+            gate_ids: &[usize],
+        ) -> Result<(), ComponentError> {
+            let mut circuit = self.to_circuit();
+            circuit.components.clear();
+            circuit.gates.retain(|gate| gate_ids.contains(&gate.id));
+            circuit.connections.retain(|connection| {
+                gate_ids.contains(&connection.output.0) && gate_ids.contains(&connection.input.0)
+            });
+
+            let component = component::define(name, circuit, &self.components)?;
+            self.components.push(component);
+            Ok(())
+        }
+
+        pub(crate) fn component_names(&self) -> impl Iterator<Item = &str> + '_ {
+            self.components
+                .iter()
+                .map(|component| component.name.as_str())
+        }
+
+        pub(crate) fn gates_in_rect(&self, corner: Vec2, other_corner: Vec2) -> Vec<usize> {
+            let (min, max) = (corner.min(other_corner), corner.max(other_corner));
+            self.gates
+                .iter()
+                .filter(|(_, pos)| pos.cmpge(min).all() && pos.cmple(max).all())
+                .map(|(id, _)| id)
+                .collect()
+        }
+
+        fn insert_pos(&mut self, gate_id: usize, pos: Vec2) {
+            let pos_id = self.gates.insert(pos);
+            debug_assert_eq!(pos_id, gate_id, "{IN_SYNC}");
+            self.revision += 1;
+        }
+
+        pub(crate) fn remove_gate(&mut self, gate_id: usize) -> Result<(), SimError> {
+            self.sim.remove_gate(gate_id)?;
+            self.gates.remove(gate_id);
+            self.connections
+                .retain(|(output, input)| output.0 != gate_id && input.0 != gate_id);
+            self.revision += 1;
+            Ok(())
         }
 
         pub(crate) fn add_connection(
             &mut self,
-            (input_gate_id, input_id, input_offset): (usize, usize, Vec2),
-            (output_gate_id, output_id, output_offset): (usize, usize, Vec2),
-        ) {
+            (input_gate_id, input_id, input_offset): PinAnchor,
+            (output_gate_id, output_id, output_offset): PinAnchor,
+        ) -> Result<(), SimError> {
             self.sim
-                .add_connection(output_gate_id, output_id, input_gate_id, input_id);
+                .add_connection(output_gate_id, output_id, input_gate_id, input_id)?;
             self.connections.push((
                 (output_gate_id, output_id, output_offset),
                 (input_gate_id, input_id, input_offset),
             ));
+            self.revision += 1;
+            Ok(())
+        }
+
+        /// Expression of an output, or of the signal arriving at an input,
+        /// with switches and buttons named like the inputs of the truth table.
+        pub(crate) fn expression(&self, pin: ProbePin) -> Result<Expr, ExtractError> {
+            let (inputs, _) = component::pins(&self.to_circuit());
+            let name = |id| match inputs.iter().position(|input| *input == id) {
+                Some(index) => truth_table::input_name(index),
+                None => format!("{}{id}", self.gate_name(id)),
+            };
+
+            match pin {
+                ProbePin::Input { gate, input } => {
+                    extraction::extract_input(&self.sim, gate, input, &name)
+                }
+                ProbePin::Output { gate, output } => {
+                    extraction::extract(&self.sim, gate, output, &name)
+                }
+            }
+        }
+
+        /// Adds a probe to the pin, or removes the probe it already has.
+        pub(crate) fn toggle_probe(&mut self, pin: ProbePin) {
+            let probe = self.sim.probes().find(|(_, probe)| probe.pin() == pin);
+            match probe {
+                Some((probe_id, _)) => {
+                    self.sim.remove_probe(probe_id).expect("probe exists");
+                }
+                None => {
+                    self.sim.add_probe(pin).expect(IN_SYNC);
+                }
+            }
         }
 
-        pub(crate) fn remove_connection(&mut self, input: (usize, usize), output: (usize, usize)) {
+        /// Writes the history of the probes as a value change dump, with a
+        /// scope for each gate named by kind and id like in saved circuits.
+        pub(crate) fn write_vcd(&self, out: &mut impl std::io::Write) -> std::io::Result<()> {
+            let scope = |id| format!("{}{id}", self.sim.get_gate_kind(id).expect(IN_SYNC));
+            vcd::write(&self.sim, &self.registry, &scope, out)
+        }
+
+        /// Probes in the order they were added, with a label naming their pin.
+        pub(crate) fn probes(&self) -> impl Iterator<Item = (String, &Probe)> + '_ {
+            self.sim.probes().map(|(_, probe)| {
+                let label = match probe.pin() {
+                    ProbePin::Input { gate, input } => {
+                        format!("{}{gate} in {input}", self.gate_name(gate))
+                    }
+                    ProbePin::Output { gate, output } => {
+                        format!("{}{gate} out {output}", self.gate_name(gate))
+                    }
+                };
+                (label, probe)
+            })
+        }
+
+        pub(crate) fn gate_delay(&self, gate_id: usize) -> u32 {
+            self.sim.get_gate_delay(gate_id).expect(IN_SYNC)
+        }
+
+        pub(crate) fn set_gate_delay(&mut self, gate_id: usize, delay: u32) {
+            self.sim.set_gate_delay(gate_id, delay).expect(IN_SYNC)
+        }
+
+        pub(crate) fn time(&self) -> u64 {
+            self.sim.time()
+        }
+
+        pub(crate) fn net_mode(&self) -> NetMode {
+            self.sim.net_mode()
+        }
+
+        pub(crate) fn set_net_mode(&mut self, net_mode: NetMode) -> Result<(), SimError> {
+            self.sim.set_net_mode(net_mode)?;
+            self.revision += 1;
+            Ok(())
+        }
+
+        pub(crate) fn is_conflicted(&self, gate_id: usize, input_id: usize) -> bool {
+            self.sim.is_conflicted(gate_id, input_id).expect(IN_SYNC)
+        }
+
+        pub(crate) fn remove_connection(
+            &mut self,
+            input: (usize, usize),
+            output: (usize, usize),
+        ) -> Result<(), SimError> {
             self.sim
-                .remove_connection(output.0, output.1, input.0, input.1);
+                .remove_connection(output.0, output.1, input.0, input.1)?;
 
             self.connections.retain(
                 |((output_gate_id, output_id, _), (input_gate_id, input_id, _))| {
                     ((*output_gate_id, *output_id), (*input_gate_id, *input_id)) != (output, input)
                 },
-            )
+            );
+            self.revision += 1;
+            Ok(())
         }
 
         pub(crate) fn simulate(&mut self) {
             self.sim.simulate()
         }
 
+        pub(crate) fn settle(&mut self, max_iterations: usize) -> Result<usize, Oscillation> {
+            self.sim.settle(max_iterations)
+        }
+
+        pub(crate) fn gate_name(&self, gate_id: usize) -> &str {
+            self.sim.get_gate_name(gate_id).expect(IN_SYNC)
+        }
+
+        /// How the gate is drawn, custom components are plain boxes.
+        pub(crate) fn shape(&self, gate_id: usize) -> Shape {
+            self.registry
+                .shape(self.sim.get_gate_kind(gate_id).expect(IN_SYNC))
+        }
+
+        /// Whether the gate is a memory which only its file can fill.
+        pub(crate) fn is_read_only(&self, gate_id: usize) -> bool {
+            self.sim
+                .get_gate_kind(gate_id)
+                .is_ok_and(|kind| kind == Rom::KIND)
+        }
+
+        /// Name of the pin from the registry, `None` for components.
+        pub(crate) fn pin_name(&self, pin: ProbePin) -> Option<String> {
+            let gate_id = match pin {
+                ProbePin::Input { gate, .. } | ProbePin::Output { gate, .. } => gate,
+            };
+            let info = self
+                .registry
+                .get(self.sim.get_gate_kind(gate_id).expect(IN_SYNC))?;
+            let (inputs, outputs) = self.sim.get_pin_widths(gate_id).expect(IN_SYNC);
+            Some(match pin {
+                ProbePin::Input { input, .. } => info.input_name(input, inputs.len()),
+                ProbePin::Output { output, .. } => info.output_name(output, outputs.len()),
+            })
+        }
+
+        /// Words and data width of a memory, `None` for other or removed gates.
+        pub(crate) fn memory(&self, gate_id: usize) -> Option<(&[u64], usize)> {
+            let words = self.sim.memory(gate_id).ok()?;
+            let (_, outputs) = self.sim.get_pin_widths(gate_id).ok()?;
+            (!words.is_empty()).then(|| (words, outputs[0]))
+        }
+
+        pub(crate) fn write_memory(&mut self, gate_id: usize, address: usize, value: u64) {
+            self.sim
+                .write_memory(gate_id, address, value)
+                .expect(IN_SYNC)
+        }
+
+        pub(crate) fn press(&mut self, gate_id: usize) -> Result<(), SimError> {
+            self.sim.press(gate_id)
+        }
+
+        pub(crate) fn release(&mut self, gate_id: usize) -> Result<(), SimError> {
+            self.sim.release(gate_id)
+        }
+
         pub(crate) fn gate_iter_mut(
             &mut self,
-        ) -> impl Iterator<Item = (usize, &mut Vec2, &str, (&[bool], &[bool]))> + '_ {
+        ) -> impl Iterator<Item = (usize, &mut Vec2, &str, Shape, PinValues<'_>)> + '_ {
             self.gates.iter_mut().map(|(id, pos)| {
-                let name = self.sim.get_gate_name(*id);
-                let state = self.sim.get_gate_state(*id);
-                (*id, pos, name, state)
+                let name = self.sim.get_gate_name(id).expect(IN_SYNC);
+                let shape = self
+                    .registry
+                    .shape(self.sim.get_gate_kind(id).expect(IN_SYNC));
+                let (input_widths, output_widths) = self.sim.get_pin_widths(id).expect(IN_SYNC);
+                let inputs = (0..input_widths.len())
+                    .map(|input| self.sim.get_input(id, input).expect(IN_SYNC))
+                    .collect();
+                let outputs = (0..output_widths.len())
+                    .map(|output| self.sim.get_output(id, output).expect(IN_SYNC))
+                    .collect();
+                (id, pos, name, shape, (inputs, outputs))
             })
         }
 
         pub(crate) fn connection_iter(
             &self,
-        ) -> impl Iterator<Item = (((usize, usize, Vec2), bool), ((usize, usize, Vec2), bool))> + '_
-        {
+        ) -> impl Iterator<Item = (PinState<'_>, PinState<'_>)> + '_ {
             self.connections.iter().map(
                 |(
                     (output_gate_id, output_id, output_offset),
                     (input_gate_id, input_id, input_offset),
                 )| {
-                    let output_state = self.sim.get_gate_state(*output_gate_id).1[*output_id];
-                    let input_state = self.sim.get_gate_state(*input_gate_id).0[*input_id];
-                    let output_pos = self.gates[output_gate_id] + *output_offset;
-                    let input_pos = self.gates[input_gate_id] + *input_offset;
+                    let output_state = self
+                        .sim
+                        .get_output(*output_gate_id, *output_id)
+                        .expect(IN_SYNC);
+                    let input_state = self
+                        .sim
+                        .get_input(*input_gate_id, *input_id)
+                        .expect(IN_SYNC);
+                    let output_pos = self.gates[*output_gate_id] + *output_offset;
+                    let input_pos = self.gates[*input_gate_id] + *input_offset;
 
                     (
                         ((*output_gate_id, *output_id, output_pos), output_state),
@@ -199,25 +761,173 @@ mod board {
         }
 
         pub(crate) fn gate_pos(&self, gate_id: usize) -> Vec2 {
-            self.gates[&gate_id]
+            self.gates[gate_id]
+        }
+
+        pub(crate) fn to_circuit(&self) -> Circuit {
+            let gates = self
+                .gates
+                .iter()
+                .map(|(id, pos)| GateEntry {
+                    id,
+                    kind: self.sim.get_gate_kind(id).expect(IN_SYNC).to_owned(),
+                    pos: (*pos).into(),
+                    params: self.sim.get_gate_params(id).expect(IN_SYNC),
+                })
+                .collect();
+
+            let connections = self
+                .connections
+                .iter()
+                .map(|(output, input)| ConnectionEntry {
+                    output: (output.0, output.1, output.2.into()),
+                    input: (input.0, input.1, input.2.into()),
+                })
+                .collect();
+
+            Circuit {
+                components: self.components.clone(),
+                nets: self.sim.net_mode(),
+                gates,
+                connections,
+            }
         }
+
+        /// Builds a new board from the circuit, gate ids from the circuit are
+        /// not preserved.
+        pub(crate) fn from_circuit(circuit: &Circuit) -> Result<BoardSimulation, LoadError> {
+            let mut board = BoardSimulation::new();
+            let mut ids = HashMap::new();
+
+            board
+                .set_net_mode(circuit.nets)
+                .map_err(LoadError::InvalidConnection)?;
+
+            for gate in &circuit.gates {
+                let boxed = component::create(
+                    &gate.kind,
+                    &gate.params,
+                    &board.registry,
+                    &circuit.components,
+                )?;
+
+                let gate_id = board.sim.add_boxed_gate(boxed);
+                board.insert_pos(gate_id, gate.pos.into());
+                ids.insert(gate.id, gate_id);
+            }
+
+            for ConnectionEntry { output, input } in &circuit.connections {
+                let id = |file_id| {
+                    ids.get(&file_id)
+                        .copied()
+                        .ok_or(LoadError::UnknownGate(file_id))
+                };
+                board
+                    .add_connection(
+                        (id(input.0)?, input.1, input.2.into()),
+                        (id(output.0)?, output.1, output.2.into()),
+                    )
+                    .map_err(LoadError::InvalidConnection)?;
+            }
+
+            board.components = circuit.components.clone();
+
+            Ok(board)
+        }
+    }
+}
+
+/// Adds the gates computing the expression to the board, returns the status
+/// to show.
+fn synthesize(simulation: &mut BoardSimulation, expr: &Expr) -> String {
+    let origin = Vec2::new(screen_width() / 4., screen_height() / 4.);
+    match simulation.add_circuit(&synthesis::synthesize(std::slice::from_ref(expr)), origin) {
+        Ok(()) => format!("Synthesized {expr}"),
+        Err(err) => err.to_string(),
+    }
+}
+
+/// Outputs of a truth table typed one character per row, the number of rows
+/// has to be a power of two.
+fn parse_outputs(source: &str) -> Option<Vec<Logic>> {
+    let column = source
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .map(|c| match c {
+            '0' => Some(Logic::Zero),
+            '1' => Some(Logic::One),
+            'x' | 'X' => Some(Logic::X),
+            _ => None,
+        })
+        .collect::<Option<Vec<_>>>()?;
+    let rows = column.len();
+    (rows >= 2 && rows.is_power_of_two() && rows <= 1 << truth_table::MAX_INPUTS).then_some(column)
+}
+
+/// Ticks "Step until stable" simulates before it reports an oscillation.
+const MAX_SETTLE_TICKS: usize = 1000;
+
+/// Most inputs of an expression simplified on hover, simplification takes
+/// exponential time in them.
+const MAX_SIMPLIFY_INPUTS: usize = 8;
+
+/// Runs the command line interface when given any arguments, see [`cli`],
+/// otherwise opens the editor.
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.is_empty() {
+        macroquad::Window::new("logic-sim", editor());
+        return;
+    }
+
+    if let Err(err) = cli::run(&args, &GateRegistry::default()) {
+        eprintln!("{err}");
+        std::process::exit(err.exit_code());
     }
 }
 
-#[macroquad::main("logic-sim")]
-async fn main() {
+async fn editor() {
     let mut simulation = BoardSimulation::new();
 
     let mut dragging: Option<(usize, Vec2)> = None;
     let mut selected_input: Option<(usize, usize, Vec2)> = None;
     let mut selected_output: Option<(usize, usize, Vec2)> = None;
+    let mut pressed: Option<usize> = None;
+    let mut selecting: Option<Vec2> = None;
+    let mut selection: Vec<usize> = Vec::new();
     let mut to_remove: Option<usize> = None;
+    let mut hovered: Option<usize> = None;
+    let mut hovered_pin: Option<ProbePin> = None;
+    // the label of the hovered pin, kept until the pin or the circuit changes
+    let mut pin_label: Option<((ProbePin, u64), String)> = None;
+    let mut waveform_zoom = 8f32;
+    // gates of the loop found by the last "Step until stable"
+    let mut oscillating: Vec<usize> = Vec::new();
+    // shown in its own window until closed
+    let mut truth_table: Option<TruthTable> = None;
+    let mut probe_wire: Option<ProbePin> = None;
+    // memory shown in the hex editor until closed
+    let mut memory_view: Option<usize> = None;
+    let mut memory_address = String::from("0");
+    let mut memory_value = String::new();
     let mut connection_to_remove: Option<((usize, usize), (usize, usize))> = None;
 
     let blackish = Color::from_rgba(0x1e, 0x1e, 0x1e, 0xff);
     let mut last_update = get_time();
     let mut frequency = 10f32;
+    let mut clock_period = 10f32;
+    let mut bus_width = 8f32;
+    let mut gate_inputs = 2f32;
+    let mut address_width = 4f32;
+    let mut memory_file = String::new();
+    let mut clock_duty_cycle = 0.5f32;
+    let mut file_path = String::from("circuit.lsim");
+    let mut status = String::new();
+    let mut component_name = String::new();
     let mut elapsed_remainder = 0f64;
+    let mut expression = String::new();
+    let mut minimize = true;
+    let mut table_outputs = String::new();
 
     let skin = {
         let label_style = root_ui().style_builder().text_color(WHITE).build();
@@ -235,17 +945,37 @@ async fn main() {
             dragging = None;
         }
 
+        if is_mouse_button_released(MouseButton::Left) {
+            if let Some(gate_id) = pressed.take() {
+                simulation.release(gate_id).expect("pressed gate exists");
+            }
+        }
+
         if is_mouse_button_released(MouseButton::Right) {
             selected_input = None;
             selected_output = None;
         }
 
         if let (Some(input), Some(output)) = (selected_input, selected_output) {
-            simulation.add_connection(input, output);
+            if let Err(err) = simulation.add_connection(input, output) {
+                status = err.to_string();
+            }
             selected_input = None;
             selected_output = None;
         }
 
+        let shift_down = is_key_down(KeyCode::LeftShift) || is_key_down(KeyCode::RightShift);
+        if shift_down && is_mouse_button_pressed(MouseButton::Left) {
+            selecting = Some(mouse_position().into());
+        }
+
+        if let Some(start) = selecting {
+            if is_mouse_button_released(MouseButton::Left) {
+                selection = simulation.gates_in_rect(start, mouse_position().into());
+                selecting = None;
+            }
+        }
+
         clear_background(blackish);
 
         let period = (1.0 / frequency) as f64;
@@ -262,46 +992,158 @@ async fn main() {
             }
         }
 
-        for (gate_id, gate_pos, gate_name, gate_state) in simulation.gate_iter_mut() {
+        for (gate_id, gate_pos, gate_name, shape, gate_state) in simulation.gate_iter_mut() {
             if let Some((dragging_id, drag_pos_offset)) = dragging {
                 if dragging_id == gate_id {
                     let pos: Vec2 = mouse_position().into();
                     *gate_pos = pos - drag_pos_offset;
+                    // inputs of expressions are named top to bottom
+                    pin_label = None;
                 }
             }
 
-            let (inputs, outputs) = gate_state;
-            if let Some(mouse_hover) = draw_gate(gate_name, gate_pos.x, gate_pos.y, inputs, outputs)
-            {
+            let (inputs, outputs) = &gate_state;
+            let mouse_hover = match shape {
+                Shape::Source => draw_source(gate_name, gate_pos.x, gate_pos.y, outputs),
+                Shape::Led => draw_led(gate_name, gate_pos.x, gate_pos.y, inputs),
+                Shape::SevenSegment => {
+                    let bits: Vec<Logic> = inputs.iter().map(|pin| pin[0]).collect();
+                    let segments = bits.as_slice().try_into().unwrap();
+                    draw_seven_segment(gate_pos.x, gate_pos.y, inputs, segments)
+                }
+                Shape::HexDigit => {
+                    let bits: Vec<Logic> = inputs.iter().map(|pin| pin[0]).collect();
+                    let segments = HexDigit::segments(bits.as_slice().try_into().unwrap());
+                    draw_seven_segment(gate_pos.x, gate_pos.y, inputs, &segments)
+                }
+                _ => draw_gate(gate_name, shape, gate_pos.x, gate_pos.y, inputs, outputs),
+            };
+
+            if let Some(mouse_hover) = mouse_hover {
                 match mouse_hover {
                     GateMouseHover::Input(input_id, input_pos) => {
+                        hovered_pin = Some(ProbePin::Input {
+                            gate: gate_id,
+                            input: input_id,
+                        });
                         if is_mouse_button_pressed(MouseButton::Left) {
                             selected_input = Some((gate_id, input_id, input_pos - *gate_pos));
                         }
                     }
                     GateMouseHover::Output(output_id, output_pos) => {
+                        hovered_pin = Some(ProbePin::Output {
+                            gate: gate_id,
+                            output: output_id,
+                        });
                         if is_mouse_button_pressed(MouseButton::Left) {
                             selected_output = Some((gate_id, output_id, output_pos - *gate_pos));
                         }
                     }
                     GateMouseHover::Gate(drag_pos) => {
-                        if dragging.is_none() {
-                            if is_mouse_button_pressed(MouseButton::Left) {
-                                let offset = drag_pos - *gate_pos;
-                                dragging = Some((gate_id, offset));
-                            }
+                        hovered = Some(gate_id);
+
+                        if dragging.is_none()
+                            && selecting.is_none()
+                            && is_mouse_button_pressed(MouseButton::Left)
+                        {
+                            let offset = drag_pos - *gate_pos;
+                            dragging = Some((gate_id, offset));
                         }
 
                         if is_mouse_button_pressed(MouseButton::Right) {
                             to_remove = Some(gate_id);
                         }
                     }
+                    GateMouseHover::Control => {
+                        if pressed.is_none()
+                            && selecting.is_none()
+                            && is_mouse_button_pressed(MouseButton::Left)
+                        {
+                            pressed = Some(gate_id);
+                        }
+                    }
                 }
             }
         }
 
+        if let Some(gate_id) = hovered.take() {
+            let delay = simulation.gate_delay(gate_id);
+            let delay = if is_key_pressed(KeyCode::Equal) || is_key_pressed(KeyCode::KpAdd) {
+                delay + 1
+            } else if is_key_pressed(KeyCode::Minus) || is_key_pressed(KeyCode::KpSubtract) {
+                delay - 1
+            } else {
+                delay
+            };
+            simulation.set_gate_delay(gate_id, delay);
+
+            if is_key_pressed(KeyCode::M) && simulation.memory(gate_id).is_some() {
+                memory_view = Some(gate_id);
+            }
+
+            let (mouse_x, mouse_y) = mouse_position();
+            let label = format!("delay {} (+/-)", simulation.gate_delay(gate_id));
+            draw_text(&label, mouse_x + 12., mouse_y - 12., 20., YELLOW);
+        }
+
+        if let Some(pin) = hovered_pin.take() {
+            if is_key_pressed(KeyCode::P) {
+                simulation.toggle_probe(pin);
+            }
+
+            let key = (pin, simulation.revision());
+            let simplify = is_key_pressed(KeyCode::S);
+            if simplify || pin_label.as_ref().map(|(cached, _)| *cached) != Some(key) {
+                let label = match simulation.expression(pin) {
+                    Ok(expr) if !simplify => format!("{expr}  (S simplifies)"),
+                    Ok(expr) if expr.vars().len() > MAX_SIMPLIFY_INPUTS => {
+                        format!("{expr}  (too many inputs to simplify)")
+                    }
+                    Ok(expr) => match expr.simplify() {
+                        Ok(simplified) => format!("{expr}  =  {simplified}"),
+                        Err(_) => expr.to_string(),
+                    },
+                    Err(err) => err.to_string(),
+                };
+                let label = match simulation.pin_name(pin) {
+                    Some(name) => format!("{name}: {label}"),
+                    None => label,
+                };
+                pin_label = Some((key, label));
+            }
+            if let Some((_, label)) = &pin_label {
+                let (mouse_x, mouse_y) = mouse_position();
+                draw_text(label, mouse_x + 12., mouse_y - 12., 20., YELLOW);
+            }
+        } else {
+            pin_label = None;
+        }
+
+        if let Some(gate_id) = pressed {
+            if is_mouse_button_pressed(MouseButton::Left) {
+                simulation.press(gate_id).expect("pressed gate exists");
+            }
+        }
+
+        for gate_id in &selection {
+            let pos = simulation.gate_pos(*gate_id);
+            draw_circle(pos.x, pos.y, 6., YELLOW);
+        }
+
+        for gate_id in &oscillating {
+            let pos = simulation.gate_pos(*gate_id);
+            draw_circle(pos.x, pos.y, 6., ORANGE);
+        }
+
+        if let Some(start) = selecting {
+            let (mouse_x, mouse_y) = mouse_position();
+            let (x, y) = (start.x.min(mouse_x), start.y.min(mouse_y));
+            let (w, h) = ((start.x - mouse_x).abs(), (start.y - mouse_y).abs());
+            draw_rectangle_lines(x, y, w, h, 2., YELLOW);
+        }
+
         for (output, input) in simulation.connection_iter() {
-            let ((output_gate_id, output_id, output_pos), output_active) = output;
+            let ((output_gate_id, output_id, output_pos), output_bits) = output;
             let ((input_gate_id, input_id, input_pos), _) = input;
 
             let opos = Vec2::new(output_pos.x, output_pos.y);
@@ -328,11 +1170,17 @@ async fn main() {
 
             let mouse_over_line = is_between && cross.abs() < 1000.;
 
-            if mouse_over_line {
-                if is_mouse_button_pressed(MouseButton::Right) {
-                    connection_to_remove =
-                        Some(((input_gate_id, input_id), (output_gate_id, output_id)));
-                }
+            if mouse_over_line && is_key_pressed(KeyCode::P) {
+                // wires carry the value of their output
+                probe_wire = Some(ProbePin::Output {
+                    gate: output_gate_id,
+                    output: output_id,
+                });
+            }
+
+            if mouse_over_line && is_mouse_button_pressed(MouseButton::Right) {
+                connection_to_remove =
+                    Some(((input_gate_id, input_id), (output_gate_id, output_id)));
             }
 
             draw_line(
@@ -340,17 +1188,37 @@ async fn main() {
                 output_pos.y,
                 input_pos.x,
                 input_pos.y,
-                if mouse_over_line { 4. } else { 2. },
-                if output_active { RED } else { WHITE },
+                // buses are drawn thicker, lit while any of their bits is
+                if mouse_over_line { 4. } else { 2. } * output_bits.len().min(2) as f32,
+                if simulation.is_conflicted(input_gate_id, input_id) {
+                    ORANGE
+                } else {
+                    signal_color(output_bits, WHITE)
+                },
             );
         }
 
+        if let Some(pin) = probe_wire.take() {
+            simulation.toggle_probe(pin);
+        }
+
+        let probes: Vec<_> = simulation.probes().collect();
+        draw_waveforms(&probes, simulation.time(), waveform_zoom);
+
         if let Some(gate_id) = to_remove.take() {
-            simulation.remove_gate(gate_id);
+            if pressed == Some(gate_id) {
+                pressed = None;
+            }
+            selection.retain(|id| *id != gate_id);
+            oscillating.retain(|id| *id != gate_id);
+            simulation
+                .remove_gate(gate_id)
+                .expect("hovered gate exists");
         }
 
         if let Some((input, output)) = connection_to_remove.take() {
-            simulation.remove_connection(input, output);
+            // the connection is already gone when its gate was removed above
+            let _ = simulation.remove_connection(input, output);
         }
 
         match (selected_input, selected_output) {
@@ -371,26 +1239,321 @@ async fn main() {
 
         {
             root_ui().slider(hash!(), "Frequency (Hz)", 1f32..100f32, &mut frequency);
+            root_ui().label(None, &format!("Time: {} ticks", simulation.time()));
+            root_ui().slider(
+                hash!(),
+                "Waveform zoom (px/tick)",
+                1f32..40f32,
+                &mut waveform_zoom,
+            );
+            if root_ui().button(None, "Step until stable") {
+                oscillating.clear();
+                status = match simulation.settle(MAX_SETTLE_TICKS) {
+                    Ok(ticks) => format!("Stable after {ticks} ticks"),
+                    Err(oscillation) => {
+                        let names: Vec<_> = oscillation
+                            .cycle
+                            .iter()
+                            .map(|id| simulation.gate_name(*id))
+                            .collect();
+                        oscillating = oscillation.cycle;
+                        format!("Oscillates through {}", names.join(" -> "))
+                    }
+                };
+            }
+
+            if root_ui().button(None, "Truth table") {
+                match TruthTable::generate(&simulation.to_circuit(), simulation.registry()) {
+                    Ok(table) => truth_table = Some(table),
+                    Err(err) => status = format!("Truth table failed: {err}"),
+                }
+            }
+
+            let net_mode = simulation.net_mode();
+            if root_ui().button(None, format!("Nets: {net_mode}")) {
+                let modes = NetMode::ALL;
+                let index = modes.iter().position(|mode| *mode == net_mode).unwrap();
+                let next = modes[(index + 1) % modes.len()];
+                if let Err(err) = simulation.set_net_mode(next) {
+                    // skip the mode the board does not allow
+                    status = err.to_string();
+                    let _ = simulation.set_net_mode(modes[(index + 2) % modes.len()]);
+                }
+            }
+            if !status.is_empty() {
+                root_ui().label(None, &status);
+            }
+
+            root_ui().input_text(hash!(), "File", &mut file_path);
+            if root_ui().button(None, "Save") {
+                let circuit = simulation.to_circuit();
+                status = match std::fs::write(&file_path, circuit.to_string()) {
+                    Ok(()) => format!("Saved {file_path}"),
+                    Err(err) => format!("Save failed: {err}"),
+                };
+            }
+            root_ui().same_line(0.);
+            if root_ui().button(None, "Load") {
+                let loaded = std::fs::read_to_string(&file_path)
+                    .map_err(|err| err.to_string())
+                    .and_then(|source| Circuit::parse(&source).map_err(|err| err.to_string()))
+                    .and_then(|circuit| {
+                        BoardSimulation::from_circuit(&circuit).map_err(|err| err.to_string())
+                    });
+                status = match loaded {
+                    Ok(loaded) => {
+                        simulation = loaded;
+                        dragging = None;
+                        pressed = None;
+                        selection.clear();
+                        oscillating.clear();
+                        selected_input = None;
+                        selected_output = None;
+                        pin_label = None;
+                        memory_view = None;
+                        truth_table = None;
+                        format!("Loaded {file_path}")
+                    }
+                    Err(err) => format!("Load failed: {err}"),
+                };
+            }
+            root_ui().same_line(0.);
+            if root_ui().button(None, "Export VCD") {
+                let path = std::path::Path::new(&file_path).with_extension("vcd");
+                let mut vcd = Vec::new();
+                simulation
+                    .write_vcd(&mut vcd)
+                    .expect("writing to memory does not fail");
+                status = match std::fs::write(&path, vcd) {
+                    Ok(()) => format!("Exported probes to {}", path.display()),
+                    Err(err) => format!("Export failed: {err}"),
+                };
+            }
+
+            root_ui().label(
+                None,
+                &format!("Selected gates: {} (shift + drag)", selection.len()),
+            );
+            root_ui().input_text(hash!(), "Component name", &mut component_name);
+            if root_ui().button(None, "Create component") {
+                status = match simulation.create_component(component_name.trim(), &selection) {
+                    Ok(()) => format!("Created {}", component_name.trim()),
+                    Err(err) => err.to_string(),
+                };
+            }
+
+            root_ui().input_text(hash!(), "Expression", &mut expression);
+            root_ui().checkbox(hash!(), "Minimize", &mut minimize);
+            if root_ui().button(None, "Synthesize expression") {
+                let expr = expression.parse::<Expr>().and_then(|expr| {
+                    if minimize {
+                        expr.simplify()
+                    } else {
+                        Ok(expr)
+                    }
+                });
+                status = match expr {
+                    Ok(expr) => synthesize(&mut simulation, &expr),
+                    Err(err) => err.to_string(),
+                };
+            }
+            root_ui().input_text(hash!(), "Outputs (0/1/X per row)", &mut table_outputs);
+            if root_ui().button(None, "Synthesize truth table") {
+                status = match parse_outputs(&table_outputs) {
+                    Some(column) => {
+                        let vars: Vec<_> = (0..column.len().trailing_zeros() as usize)
+                            .map(truth_table::input_name)
+                            .collect();
+                        let expr = expression::sum_of_products(&vars, &column);
+                        synthesize(&mut simulation, &expr)
+                    }
+                    None => format!(
+                        "Expected 2 to {} rows of 0, 1 or X",
+                        1 << truth_table::MAX_INPUTS
+                    ),
+                };
+            }
+
             root_ui().label(None, "Add Gate:");
 
-            fn add_gate_btn<const INPUTS: usize, const OUTPUTS: usize>(
-                gate: impl Gate<INPUTS, OUTPUTS> + 'static,
-                simulation: &mut BoardSimulation,
-            ) {
+            root_ui().slider(
+                hash!(),
+                "Inputs / choices",
+                2f32..MAX_GATE_INPUTS as f32,
+                &mut gate_inputs,
+            );
+            root_ui().slider(hash!(), "Bus width (bits)", 1f32..16f32, &mut bus_width);
+            root_ui().slider(
+                hash!(),
+                "Address width (bits)",
+                1f32..16f32,
+                &mut address_width,
+            );
+            root_ui().input_text(hash!(), "Memory file (.hex/.bin)", &mut memory_file);
+            root_ui().slider(
+                hash!(),
+                "Clock period (ticks)",
+                2f32..64f32,
+                &mut clock_period,
+            );
+            root_ui().slider(
+                hash!(),
+                "Clock duty cycle",
+                0f32..1f32,
+                &mut clock_duty_cycle,
+            );
+
+            // every kind takes the parameters it knows from the sliders
+            let mut params = Params::default()
+                .with("inputs", gate_inputs as usize)
+                .with("outputs", gate_inputs as usize)
+                .with("width", bus_width as usize)
+                .with("data_width", bus_width as usize)
+                .with("address_width", address_width as usize)
+                .with("period", clock_period as u32)
+                .with("duty_cycle", clock_duty_cycle);
+            if !memory_file.trim().is_empty() {
+                params.set("file", memory_file.trim());
+            }
+
+            let mut clicked = None;
+            for category in simulation.registry().categories() {
+                root_ui().label(None, category);
+                for info in simulation.registry().iter() {
+                    if info.category == category
+                        && root_ui().button(None, format!("{:<5} {}", info.name, info.icon))
+                    {
+                        clicked = Some((info.kind.to_owned(), params.clone()));
+                    }
+                }
+            }
+
+            root_ui().label(None, "Components");
+            for name in simulation.component_names() {
+                if root_ui().button(None, name) {
+                    let params = Params::default().with("name", name);
+                    clicked = Some((component::KIND.to_owned(), params));
+                }
+            }
+
+            if let Some((kind, params)) = clicked {
                 let screen_middle = Vec2::new(screen_width() / 2., screen_height() / 2.);
-                if root_ui().button(None, format!("{:<5}", gate.name())) {
-                    simulation.add_gate(gate, screen_middle);
+                if let Err(err) = simulation.add_kind(&kind, &params, screen_middle) {
+                    status = err.to_string();
                 }
             }
+        }
+
+        if let Some(table) = &truth_table {
+            let mut export = None;
+            let open =
+                widgets::Window::new(hash!(), vec2(screen_width() - 320., 10.), vec2(300., 400.))
+                    .label("Truth table")
+                    .close_button(true)
+                    .ui(&mut root_ui(), |ui| {
+                        if ui.button(None, "Export CSV") {
+                            export = Some(("csv", table.to_csv()));
+                        }
+                        ui.same_line(0.);
+                        if ui.button(None, "Export Markdown") {
+                            export = Some(("md", table.to_markdown()));
+                        }
+
+                        ui.label(
+                            None,
+                            &format!("{} | {}", table.inputs.join(" "), table.outputs.join(" ")),
+                        );
+                        let values = |values: &[Logic]| {
+                            values
+                                .iter()
+                                .map(Logic::to_string)
+                                .collect::<Vec<_>>()
+                                .join(" ")
+                        };
+                        for (inputs, outputs) in &table.rows {
+                            ui.label(None, &format!("{} | {}", values(inputs), values(outputs)));
+                        }
+                    });
+
+            if let Some((extension, contents)) = export {
+                let path = std::path::Path::new(&file_path).with_extension(extension);
+                status = match std::fs::write(&path, contents) {
+                    Ok(()) => format!("Exported {}", path.display()),
+                    Err(err) => format!("Export failed: {err}"),
+                };
+            }
+            if !open {
+                truth_table = None;
+            }
+        }
 
-            add_gate_btn(And, &mut simulation);
-            add_gate_btn(Nand, &mut simulation);
-            add_gate_btn(Or, &mut simulation);
-            add_gate_btn(Nor, &mut simulation);
-            add_gate_btn(Xor, &mut simulation);
-            add_gate_btn(Xnor, &mut simulation);
-            add_gate_btn(Yes, &mut simulation);
-            add_gate_btn(Not, &mut simulation);
+        if let Some(gate_id) = memory_view {
+            let mut write = None;
+            let mut load = false;
+            let read_only = simulation.is_read_only(gate_id);
+            let open = match simulation.memory(gate_id) {
+                Some((words, data_width)) => {
+                    let name = format!("{}{gate_id}", simulation.gate_name(gate_id));
+                    let position = vec2(screen_width() - 420., 420.);
+                    widgets::Window::new(hash!(), position, vec2(400., 420.))
+                        .label("Memory")
+                        .close_button(true)
+                        .ui(&mut root_ui(), |ui| {
+                            ui.label(None, &format!("{name}, {} words", words.len()));
+                            ui.input_text(hash!(), "Address (hex)", &mut memory_address);
+                            let address = usize::from_str_radix(memory_address.trim(), 16);
+                            if read_only {
+                                ui.label(None, "Read only, filled from its memory file");
+                            } else {
+                                ui.input_text(hash!(), "Value (hex)", &mut memory_value);
+                                if ui.button(None, "Write") {
+                                    let value = u64::from_str_radix(memory_value.trim(), 16);
+                                    write = Some((address.clone(), value));
+                                }
+                                ui.same_line(0.);
+                                if ui.button(None, "Load memory file") {
+                                    load = true;
+                                }
+                            }
+
+                            // the page of 128 words around the address
+                            let page = address.unwrap_or(0).min(words.len() - 1) / 128 * 128;
+                            let digits = data_width.div_ceil(4);
+                            let end = (page + 128).min(words.len());
+                            for (row, line) in words[page..end].chunks(8).enumerate() {
+                                let words: Vec<_> = line
+                                    .iter()
+                                    .map(|word| format!("{word:0digits$x}"))
+                                    .collect();
+                                let label = format!("{:04x}: {}", page + row * 8, words.join(" "));
+                                ui.label(None, &label);
+                            }
+                        })
+                }
+                None => false,
+            };
+
+            match write {
+                Some((Ok(address), Ok(value))) => simulation.write_memory(gate_id, address, value),
+                Some(_) => status = "Address and value must be hex numbers".to_owned(),
+                None => {}
+            }
+            if load {
+                let (words, data_width) = simulation.memory(gate_id).expect("shown memory exists");
+                let address_width = words.len().trailing_zeros() as usize;
+                status = match memory::load(memory_file.trim(), address_width, data_width) {
+                    Ok(contents) => {
+                        for (address, value) in contents.into_iter().enumerate() {
+                            simulation.write_memory(gate_id, address, value);
+                        }
+                        format!("Loaded {}", memory_file.trim())
+                    }
+                    Err(err) => format!("Loading memory failed: {err}"),
+                };
+            }
+            if !open {
+                memory_view = None;
+            }
         }
 
         next_frame().await
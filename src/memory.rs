@@ -0,0 +1,387 @@
+//! Memories: a ROM whose contents come from a file and a RAM which circuits
+//! write on a clock edge. Both read asynchronously, the data output follows
+//! the address input after the delay of the gate.
+//!
+//! Contents can be read and written while simulating with
+//! [`LogicSimulation::memory`] and [`LogicSimulation::write_memory`].
+//!
+//! [`LogicSimulation::memory`]: crate::logic_simulation::LogicSimulation::memory
+//! [`LogicSimulation::write_memory`]: crate::logic_simulation::LogicSimulation::write_memory
+
+use std::{fmt, io, path::Path};
+
+use crate::{
+    gates::{DynamicGate, Params},
+    logic::{bus_value, clamp_width, set_bus_value, Logic},
+    registers::rising,
+};
+
+/// Widest address a memory takes, larger ones would need too many words.
+pub const MAX_ADDRESS_WIDTH: usize = 16;
+
+#[derive(Debug)]
+pub enum MemoryError {
+    Io(io::Error),
+    /// A token of a hex file which is neither a word nor an address.
+    Syntax {
+        line: usize,
+        token: String,
+    },
+    /// More words than the memory has.
+    TooLong(usize),
+}
+
+impl fmt::Display for MemoryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MemoryError::Io(err) => err.fmt(f),
+            MemoryError::Syntax { line, token } => {
+                write!(f, "line {line}: invalid word '{token}'")
+            }
+            MemoryError::TooLong(words) => {
+                write!(f, "contents need {words} words, the memory is smaller")
+            }
+        }
+    }
+}
+
+impl std::error::Error for MemoryError {}
+
+/// Parses hex contents: words separated by whitespace, starting at address
+/// zero. `@` followed by a hex address continues at that address and `//` or
+/// `#` start a comment, like in files for Verilog's `$readmemh`.
+pub fn parse_hex(text: &str, words: usize) -> Result<Vec<u64>, MemoryError> {
+    let mut contents = Vec::new();
+    let mut address = 0;
+    for (index, line) in text.lines().enumerate() {
+        let line = line.split("//").next().unwrap();
+        let line = line.split('#').next().unwrap();
+        for token in line.split_whitespace() {
+            let syntax = || MemoryError::Syntax {
+                line: index + 1,
+                token: token.to_owned(),
+            };
+            if let Some(target) = token.strip_prefix('@') {
+                address = usize::from_str_radix(target, 16).map_err(|_| syntax())?;
+                continue;
+            }
+
+            let word = u64::from_str_radix(token, 16).map_err(|_| syntax())?;
+            if address >= words {
+                return Err(MemoryError::TooLong(address + 1));
+            }
+            if contents.len() <= address {
+                contents.resize(address + 1, 0);
+            }
+            contents[address] = word;
+            address += 1;
+        }
+    }
+    Ok(contents)
+}
+
+/// Splits raw binary contents into words of whole bytes, least significant
+/// byte first.
+pub fn parse_binary(
+    bytes: &[u8],
+    data_width: usize,
+    words: usize,
+) -> Result<Vec<u64>, MemoryError> {
+    let bytes_per_word = data_width.div_ceil(8).max(1);
+    let contents: Vec<u64> = bytes
+        .chunks(bytes_per_word)
+        .map(|chunk| {
+            chunk
+                .iter()
+                .rev()
+                .fold(0, |word, byte| (word << 8) | *byte as u64)
+        })
+        .collect();
+    if contents.len() > words {
+        return Err(MemoryError::TooLong(contents.len()));
+    }
+    Ok(contents)
+}
+
+/// Reads the contents of a memory, files ending in `.bin` are raw binary and
+/// all others hex, see [`parse_hex`] and [`parse_binary`].
+pub fn load(
+    path: impl AsRef<Path>,
+    address_width: usize,
+    data_width: usize,
+) -> Result<Vec<u64>, MemoryError> {
+    let path = path.as_ref();
+    let words = 1 << clamp_address_width(address_width);
+    if path.extension().is_some_and(|extension| extension == "bin") {
+        let bytes = std::fs::read(path).map_err(MemoryError::Io)?;
+        parse_binary(&bytes, clamp_width(data_width), words)
+    } else {
+        let text = std::fs::read_to_string(path).map_err(MemoryError::Io)?;
+        parse_hex(&text, words)
+    }
+}
+
+/// Hex contents which [`parse_hex`] reads back, eight words per line.
+pub fn to_hex(contents: &[u64], data_width: usize) -> String {
+    let digits = data_width.div_ceil(4).max(1);
+    let mut hex = String::new();
+    for line in contents.chunks(8) {
+        let words: Vec<_> = line
+            .iter()
+            .map(|word| format!("{word:0digits$x}"))
+            .collect();
+        hex.push_str(&words.join(" "));
+        hex.push('\n');
+    }
+    hex
+}
+
+fn clamp_address_width(width: usize) -> usize {
+    width.clamp(1, MAX_ADDRESS_WIDTH)
+}
+
+/// Words of the given size, cleared to zero and with extra bits of the
+/// initial contents cut off.
+fn words(address_width: usize, data_width: usize, contents: &[u64]) -> Vec<u64> {
+    let mask = u64::MAX >> (64 - data_width);
+    let mut words = vec![0; 1 << address_width];
+    for (word, value) in words.iter_mut().zip(contents) {
+        *word = value & mask;
+    }
+    words
+}
+
+/// Drives the data output with the addressed word, all bits are unknown
+/// while the address is.
+fn read(words: &[u64], address: &[Logic], data: &mut [Logic]) {
+    match bus_value(address) {
+        Some(address) => set_bus_value(data, words[address as usize]),
+        None => data.fill(Logic::X),
+    }
+}
+
+/// Read only memory with an address input and a data output.
+pub struct Rom {
+    address_width: usize,
+    data_width: usize,
+    words: Vec<u64>,
+    /// File the contents were loaded from, kept to save the circuit.
+    file: Option<String>,
+}
+
+impl Rom {
+    pub const NAME: &'static str = "ROM";
+    pub const KIND: &'static str = "rom";
+
+    /// Creates a ROM with the contents, missing words are zero.
+    pub fn new(address_width: usize, data_width: usize, contents: &[u64]) -> Rom {
+        let address_width = clamp_address_width(address_width);
+        let data_width = clamp_width(data_width);
+        Rom {
+            address_width,
+            data_width,
+            words: words(address_width, data_width, contents),
+            file: None,
+        }
+    }
+
+    /// Creates a ROM with the contents of the file, see [`load`].
+    pub fn load(address_width: usize, data_width: usize, file: &str) -> Result<Rom, MemoryError> {
+        let contents = load(file, address_width, data_width)?;
+        Ok(Rom {
+            file: Some(file.to_owned()),
+            ..Rom::new(address_width, data_width, &contents)
+        })
+    }
+}
+
+impl DynamicGate for Rom {
+    fn name(&self) -> &'static str {
+        Self::NAME
+    }
+
+    fn kind(&self) -> &'static str {
+        Self::KIND
+    }
+
+    fn input_widths(&self) -> Vec<usize> {
+        vec![self.address_width]
+    }
+
+    fn output_widths(&self) -> Vec<usize> {
+        vec![self.data_width]
+    }
+
+    fn update(&mut self, inputs: &[Logic], outputs: &mut [Logic]) {
+        read(&self.words, inputs, outputs);
+    }
+
+    fn params(&self) -> Params {
+        let params = Params::default()
+            .with("address_width", self.address_width)
+            .with("data_width", self.data_width);
+        match &self.file {
+            Some(file) => params.with("file", file),
+            None => params,
+        }
+    }
+
+    fn memory(&self) -> &[u64] {
+        &self.words
+    }
+}
+
+/// Random access memory with separate data input and output.
+///
+/// Inputs are the address, the data to write, `write` (default low) and the
+/// clock, the addressed word is written on the rising edge of the clock while
+/// `write` is high. Writes with an unknown address or data are dropped, as
+/// are all while `write` is unknown.
+pub struct Ram {
+    address_width: usize,
+    data_width: usize,
+    words: Vec<u64>,
+    clock: Logic,
+}
+
+impl Ram {
+    pub const NAME: &'static str = "RAM";
+    pub const KIND: &'static str = "ram";
+
+    pub fn new(address_width: usize, data_width: usize) -> Ram {
+        let address_width = clamp_address_width(address_width);
+        let data_width = clamp_width(data_width);
+        Ram {
+            address_width,
+            data_width,
+            words: words(address_width, data_width, &[]),
+            clock: Logic::Z,
+        }
+    }
+}
+
+impl DynamicGate for Ram {
+    fn name(&self) -> &'static str {
+        Self::NAME
+    }
+
+    fn kind(&self) -> &'static str {
+        Self::KIND
+    }
+
+    fn input_widths(&self) -> Vec<usize> {
+        vec![self.address_width, self.data_width, 1, 1]
+    }
+
+    fn output_widths(&self) -> Vec<usize> {
+        vec![self.data_width]
+    }
+
+    fn update(&mut self, inputs: &[Logic], outputs: &mut [Logic]) {
+        let (address, inputs) = inputs.split_at(self.address_width);
+        let (data, controls) = inputs.split_at(self.data_width);
+        let (write, clock) = (controls[0], controls[1]);
+
+        if rising(&mut self.clock, clock) && write == Logic::One {
+            if let (Some(address), Some(data)) = (bus_value(address), bus_value(data)) {
+                self.words[address as usize] = data;
+            }
+        }
+
+        read(&self.words, address, outputs);
+    }
+
+    fn params(&self) -> Params {
+        Params::default()
+            .with("address_width", self.address_width)
+            .with("data_width", self.data_width)
+    }
+
+    fn memory(&self) -> &[u64] {
+        &self.words
+    }
+
+    fn write_memory(&mut self, address: usize, value: u64) {
+        let mask = u64::MAX >> (64 - self.data_width);
+        if let Some(word) = self.words.get_mut(address) {
+            *word = value & mask;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::gates::tests::{N, X, Y, Z};
+
+    #[test]
+    fn hex() {
+        let text = "// boot\n1f 2 # two\n@6 ff\n";
+        assert_eq!(parse_hex(text, 8).unwrap(), [0x1f, 2, 0, 0, 0, 0, 0xff]);
+        assert!(matches!(
+            parse_hex("1 2\n3 zz", 8),
+            Err(MemoryError::Syntax { line: 2, .. })
+        ));
+        assert!(matches!(parse_hex("@8 1", 8), Err(MemoryError::TooLong(9))));
+
+        let contents = [0x1f, 2, 0, 0, 0, 0, 0xff, 0, 3];
+        let hex = to_hex(&contents, 8);
+        assert_eq!(hex, "1f 02 00 00 00 00 ff 00\n03\n");
+        assert_eq!(parse_hex(&hex, 16).unwrap(), contents);
+    }
+
+    #[test]
+    fn binary() {
+        assert_eq!(parse_binary(&[1, 2, 3], 8, 4).unwrap(), [1, 2, 3]);
+        assert_eq!(parse_binary(&[1, 2, 3], 12, 4).unwrap(), [0x201, 3]);
+        assert!(parse_binary(&[0; 5], 8, 4).is_err());
+    }
+
+    #[test]
+    fn rom() {
+        let mut rom = Rom::new(2, 4, &[0x5, 0x1a]);
+        let mut outputs = [Z; 4];
+        // the high bit of the second word is cut off
+        for (address, expected) in [
+            ([N, N], [Y, N, Y, N]),
+            ([Y, N], [N, Y, N, Y]),
+            ([X, N], [X; 4]),
+        ] {
+            rom.update(&address, &mut outputs);
+            assert_eq!(outputs, expected);
+        }
+        assert_eq!(rom.memory(), [0x5, 0xa, 0, 0]);
+    }
+
+    #[test]
+    fn ram() {
+        let mut ram = Ram::new(1, 2);
+        let mut outputs = [Z; 2];
+        // address, data, write, clock
+        let mut inputs = [Y, Y, N, Y, N];
+        for clock in [N, Y] {
+            inputs[4] = clock;
+            ram.update(&inputs, &mut outputs);
+        }
+        assert_eq!(outputs, [Y, N]);
+        assert_eq!(ram.memory(), [0, 1]);
+
+        // no write while it is low or unknown
+        for write in [N, X, Z] {
+            inputs[1..4].copy_from_slice(&[N, Y, write]);
+            for clock in [N, Y] {
+                inputs[4] = clock;
+                ram.update(&inputs, &mut outputs);
+            }
+            assert_eq!(outputs, [Y, N]);
+        }
+
+        ram.write_memory(0, 7);
+        ram.write_memory(2, 1);
+        inputs[0] = N;
+        ram.update(&inputs, &mut outputs);
+        assert_eq!(outputs, [Y, Y]);
+        assert_eq!(ram.memory(), [3, 1]);
+    }
+}
@@ -0,0 +1,359 @@
+//! Blocks which route or encode signals by an index: multiplexer,
+//! demultiplexer, binary decoder and priority encoder.
+//!
+//! Indices are buses just wide enough for the number of choices, with the
+//! first bit as the least significant one. An unknown index makes all outputs
+//! unknown, as does an index past the last choice. Blocks have 2 to 64
+//! choices and data buses of 1 to 64 bits.
+
+use crate::{
+    gates::{DynamicGate, Params},
+    logic::{bus_value, clamp_width, set_bus_value, Logic},
+};
+
+/// Most choices a block has.
+pub const MAX_CHOICES: usize = 64;
+
+fn clamp_choices(choices: usize) -> usize {
+    choices.clamp(2, MAX_CHOICES)
+}
+
+/// Bits of an index selecting one of the choices.
+pub fn select_width(choices: usize) -> usize {
+    (usize::BITS - (choices.max(2) - 1).leading_zeros()) as usize
+}
+
+/// Index on the bus if it is known and less than `choices`.
+fn index(bits: &[Logic], choices: usize) -> Option<usize> {
+    bus_value(bits)
+        .map(|index| index as usize)
+        .filter(|index| *index < choices)
+}
+
+/// Passes the data input chosen by the select input to the output. Inputs
+/// are the select bus followed by the data inputs.
+pub struct Multiplexer {
+    inputs: usize,
+    width: usize,
+}
+
+impl Multiplexer {
+    pub const NAME: &'static str = "MUX";
+    pub const KIND: &'static str = "multiplexer";
+
+    /// Multiplexer of `inputs` data inputs, each `width` bits wide.
+    pub fn new(inputs: usize, width: usize) -> Multiplexer {
+        Multiplexer {
+            inputs: clamp_choices(inputs),
+            width: clamp_width(width),
+        }
+    }
+}
+
+impl DynamicGate for Multiplexer {
+    fn name(&self) -> &'static str {
+        Self::NAME
+    }
+
+    fn kind(&self) -> &'static str {
+        Self::KIND
+    }
+
+    fn input_widths(&self) -> Vec<usize> {
+        let mut widths = vec![self.width; self.inputs + 1];
+        widths[0] = select_width(self.inputs);
+        widths
+    }
+
+    fn output_widths(&self) -> Vec<usize> {
+        vec![self.width]
+    }
+
+    fn update(&mut self, inputs: &[Logic], outputs: &mut [Logic]) {
+        let (select, data) = inputs.split_at(select_width(self.inputs));
+        match index(select, self.inputs) {
+            Some(index) => {
+                let chosen = &data[index * self.width..][..self.width];
+                for (output, input) in outputs.iter_mut().zip(chosen) {
+                    *output = input.read();
+                }
+            }
+            None => outputs.fill(Logic::X),
+        }
+    }
+
+    fn params(&self) -> Params {
+        Params::default()
+            .with("inputs", self.inputs)
+            .with("width", self.width)
+    }
+}
+
+/// Passes the data input to the output chosen by the select input, the other
+/// outputs are low. Inputs are the select bus and the data.
+pub struct Demultiplexer {
+    outputs: usize,
+    width: usize,
+}
+
+impl Demultiplexer {
+    pub const NAME: &'static str = "DEMUX";
+    pub const KIND: &'static str = "demultiplexer";
+
+    /// Demultiplexer of `outputs` data outputs, each `width` bits wide.
+    pub fn new(outputs: usize, width: usize) -> Demultiplexer {
+        Demultiplexer {
+            outputs: clamp_choices(outputs),
+            width: clamp_width(width),
+        }
+    }
+}
+
+impl DynamicGate for Demultiplexer {
+    fn name(&self) -> &'static str {
+        Self::NAME
+    }
+
+    fn kind(&self) -> &'static str {
+        Self::KIND
+    }
+
+    fn input_widths(&self) -> Vec<usize> {
+        vec![select_width(self.outputs), self.width]
+    }
+
+    fn output_widths(&self) -> Vec<usize> {
+        vec![self.width; self.outputs]
+    }
+
+    fn update(&mut self, inputs: &[Logic], outputs: &mut [Logic]) {
+        let (select, data) = inputs.split_at(select_width(self.outputs));
+        match index(select, self.outputs) {
+            Some(index) => {
+                outputs.fill(Logic::Zero);
+                let chosen = &mut outputs[index * self.width..][..self.width];
+                for (output, input) in chosen.iter_mut().zip(data) {
+                    *output = input.read();
+                }
+            }
+            None => outputs.fill(Logic::X),
+        }
+    }
+
+    fn params(&self) -> Params {
+        Params::default()
+            .with("outputs", self.outputs)
+            .with("width", self.width)
+    }
+}
+
+/// Sets the output chosen by its input high and all others low.
+pub struct Decoder {
+    outputs: usize,
+}
+
+impl Decoder {
+    pub const NAME: &'static str = "DEC";
+    pub const KIND: &'static str = "decoder";
+
+    pub fn new(outputs: usize) -> Decoder {
+        Decoder {
+            outputs: clamp_choices(outputs),
+        }
+    }
+}
+
+impl DynamicGate for Decoder {
+    fn name(&self) -> &'static str {
+        Self::NAME
+    }
+
+    fn kind(&self) -> &'static str {
+        Self::KIND
+    }
+
+    fn input_widths(&self) -> Vec<usize> {
+        vec![select_width(self.outputs)]
+    }
+
+    fn output_widths(&self) -> Vec<usize> {
+        vec![1; self.outputs]
+    }
+
+    fn update(&mut self, inputs: &[Logic], outputs: &mut [Logic]) {
+        match index(inputs, self.outputs) {
+            Some(index) => {
+                for (output, value) in outputs.iter_mut().enumerate() {
+                    *value = (output == index).into();
+                }
+            }
+            None => outputs.fill(Logic::X),
+        }
+    }
+
+    fn params(&self) -> Params {
+        Params::default().with("outputs", self.outputs)
+    }
+}
+
+/// Outputs the index of the last high input and whether any input is high.
+/// Floating inputs count as low, an unknown input makes the outputs unknown
+/// unless a later input is high.
+pub struct PriorityEncoder {
+    inputs: usize,
+}
+
+impl PriorityEncoder {
+    pub const NAME: &'static str = "PENC";
+    pub const KIND: &'static str = "priority_encoder";
+
+    pub fn new(inputs: usize) -> PriorityEncoder {
+        PriorityEncoder {
+            inputs: clamp_choices(inputs),
+        }
+    }
+}
+
+impl DynamicGate for PriorityEncoder {
+    fn name(&self) -> &'static str {
+        Self::NAME
+    }
+
+    fn kind(&self) -> &'static str {
+        Self::KIND
+    }
+
+    fn input_widths(&self) -> Vec<usize> {
+        vec![1; self.inputs]
+    }
+
+    fn output_widths(&self) -> Vec<usize> {
+        vec![select_width(self.inputs), 1]
+    }
+
+    fn update(&mut self, inputs: &[Logic], outputs: &mut [Logic]) {
+        let (index, valid) = outputs.split_at_mut(select_width(self.inputs));
+        let highest = inputs
+            .iter()
+            .rposition(|input| !matches!(input, Logic::Zero | Logic::Z));
+        match highest.map(|highest| (highest, inputs[highest])) {
+            Some((highest, Logic::One)) => {
+                set_bus_value(index, highest as u64);
+                valid[0] = Logic::One;
+            }
+            Some(_) => {
+                index.fill(Logic::X);
+                valid[0] = Logic::X;
+            }
+            None => {
+                index.fill(Logic::Zero);
+                valid[0] = Logic::Zero;
+            }
+        }
+    }
+
+    fn params(&self) -> Params {
+        Params::default().with("inputs", self.inputs)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gates::tests::*;
+
+    #[test]
+    fn select_widths() {
+        let widths = [2, 3, 4, 5, 8, 9, 16].map(select_width);
+        assert_eq!(widths, [1, 2, 2, 3, 3, 4, 4]);
+    }
+
+    #[test]
+    fn multiplexer() {
+        let mux = Multiplexer::new(3, 1);
+        assert_eq!(mux.input_widths(), [2, 1, 1, 1]);
+
+        // select, data 0 to 2
+        #[rustfmt::skip]
+        let table = [
+            ([N, N, Y, N, N], [Y]),
+            ([Y, N, Y, N, N], [N]),
+            ([N, Y, Y, N, Y], [Y]),
+            ([N, Y, Y, N, Z], [X]),
+            ([Y, Y, Y, Y, Y], [X]),
+            ([X, N, Y, Y, Y], [X]),
+        ];
+
+        for row in table {
+            test_dynamic_gate(Multiplexer::new(3, 1), (&row.0, &row.1));
+        }
+
+        let inputs = [Y, N, N, Y, Y];
+        test_dynamic_gate(Multiplexer::new(2, 2), (&inputs, &[Y, Y]));
+    }
+
+    #[test]
+    fn limits() {
+        let mux = Multiplexer::new(1, 0);
+        assert_eq!(
+            (mux.input_widths(), mux.output_widths()),
+            (vec![1, 1, 1], vec![1])
+        );
+
+        let mux = Multiplexer::new(2, 1000);
+        assert_eq!(mux.input_widths(), [1, 64, 64]);
+
+        let demux = Demultiplexer::new(1000, 1000);
+        assert_eq!(demux.input_widths(), [6, 64]);
+        assert_eq!(demux.output_widths(), [64; 64]);
+    }
+
+    #[test]
+    fn demultiplexer() {
+        // select, data
+        #[rustfmt::skip]
+        let table = [
+            ([N, N, Y], [Y, N, N, N]),
+            ([Y, Y, Y], [N, N, N, Y]),
+            ([Y, N, Z], [N, X, N, N]),
+            ([X, N, Y], [X, X, X, X]),
+        ];
+
+        for row in table {
+            test_dynamic_gate(Demultiplexer::new(4, 1), (&row.0, &row.1));
+        }
+    }
+
+    #[test]
+    fn decoder() {
+        #[rustfmt::skip]
+        let table = [
+            ([N, N], [Y, N, N]),
+            ([Y, N], [N, Y, N]),
+            ([N, Y], [N, N, Y]),
+            ([Y, Y], [X, X, X]),
+            ([Z, N], [X, X, X]),
+        ];
+
+        for row in table {
+            test_dynamic_gate(Decoder::new(3), (&row.0, &row.1));
+        }
+    }
+
+    #[test]
+    fn priority_encoder() {
+        // index, valid
+        #[rustfmt::skip]
+        let table = [
+            ([N, N, N, N], [N, N, N]),
+            ([Y, N, N, N], [N, N, Y]),
+            ([Y, Y, Z, N], [Y, N, Y]),
+            ([X, N, Y, Z], [N, Y, Y]),
+            ([Y, N, X, N], [X, X, X]),
+        ];
+
+        for row in table {
+            test_dynamic_gate(PriorityEncoder::new(4), (&row.0, &row.1));
+        }
+    }
+}
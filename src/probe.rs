@@ -0,0 +1,106 @@
+//! Probes record the values of pins tick by tick, see
+//! [`crate::logic_simulation::LogicSimulation::add_probe`].
+
+use std::collections::VecDeque;
+
+use crate::logic::Logic;
+
+/// Value changes a probe keeps, older ones are dropped first.
+pub const HISTORY: usize = 4096;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProbePin {
+    Input {
+        gate: usize,
+        input: usize,
+    },
+    /// Also the value of every wire leaving the output.
+    Output {
+        gate: usize,
+        output: usize,
+    },
+}
+
+impl ProbePin {
+    pub fn gate(self) -> usize {
+        match self {
+            ProbePin::Input { gate, .. } | ProbePin::Output { gate, .. } => gate,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Probe {
+    pin: ProbePin,
+    /// Values with the tick from which on they hold, oldest first.
+    changes: VecDeque<(u64, Box<[Logic]>)>,
+}
+
+impl Probe {
+    pub(crate) fn new(pin: ProbePin, time: u64, value: &[Logic]) -> Probe {
+        Probe {
+            pin,
+            changes: VecDeque::from([(time, value.into())]),
+        }
+    }
+
+    /// Keeps the value when it differs from the last one.
+    pub(crate) fn record(&mut self, time: u64, value: &[Logic]) {
+        if self
+            .changes
+            .back()
+            .is_some_and(|(_, last)| **last == *value)
+        {
+            return;
+        }
+        if self.changes.len() == HISTORY {
+            self.changes.pop_front();
+        }
+        self.changes.push_back((time, value.into()));
+    }
+
+    pub fn pin(&self) -> ProbePin {
+        self.pin
+    }
+
+    /// Values with the tick from which on they hold, oldest first.
+    pub fn changes(&self) -> impl DoubleEndedIterator<Item = (u64, &[Logic])> + '_ {
+        self.changes.iter().map(|(time, value)| (*time, &**value))
+    }
+
+    /// Value during the tick, `None` before the oldest recorded change.
+    pub fn value_at(&self, time: u64) -> Option<&[Logic]> {
+        let index = self.changes.partition_point(|(start, _)| *start <= time);
+        index.checked_sub(1).map(|index| &*self.changes[index].1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const Y: Logic = Logic::One;
+    const N: Logic = Logic::Zero;
+
+    #[test]
+    fn record() {
+        let pin = ProbePin::Output { gate: 0, output: 0 };
+        let mut probe = Probe::new(pin, 2, &[N]);
+        probe.record(3, &[N]);
+        probe.record(5, &[Y]);
+
+        assert_eq!(
+            probe.changes().collect::<Vec<_>>(),
+            [(2, &[N][..]), (5, &[Y][..])]
+        );
+        assert_eq!(probe.value_at(1), None);
+        assert_eq!(probe.value_at(4), Some(&[N][..]));
+        assert_eq!(probe.value_at(9), Some(&[Y][..]));
+
+        for time in 0..HISTORY as u64 {
+            probe.record(6 + time, &[[N, Y][time as usize % 2]]);
+        }
+        assert_eq!(probe.changes().count(), HISTORY);
+        assert_eq!(probe.value_at(5), None);
+    }
+}
@@ -0,0 +1,337 @@
+//! Clocked storage components: registers, counters and shift registers.
+//!
+//! All of them change their state on the rising edge of their last input, the
+//! clock, and start cleared to zero. A floating control input reads as its
+//! default, so a component works without wiring controls it does not need,
+//! while an unknown control at a clock edge makes the whole state unknown.
+//! Widths are limited to 1 to 64 bits.
+
+use crate::{
+    gates::{DynamicGate, Params},
+    logic::{clamp_width, set_bus_value, Logic},
+};
+
+/// Value of a control input, `None` when unknown.
+pub(crate) fn control(value: Logic, default: bool) -> Option<bool> {
+    match value {
+        Logic::Z => Some(default),
+        value => value.to_bool(),
+    }
+}
+
+/// Whether the clock went from low to high since the last update.
+pub(crate) fn rising(last: &mut Logic, clock: Logic) -> bool {
+    let rising = *last == Logic::Zero && clock == Logic::One;
+    *last = clock;
+    rising
+}
+
+/// Stores a word on a clock edge.
+///
+/// Inputs are the data, `load` (default high) which enables storing, the
+/// output enable (default high) and the clock. While the output is disabled it
+/// floats, so several registers can drive a shared bus.
+pub struct Register {
+    value: Vec<Logic>,
+    clock: Logic,
+}
+
+impl Register {
+    pub const NAME: &'static str = "REG";
+    pub const KIND: &'static str = "register";
+
+    pub fn new(width: usize) -> Register {
+        Register {
+            value: vec![Logic::Zero; clamp_width(width)],
+            clock: Logic::Z,
+        }
+    }
+}
+
+impl DynamicGate for Register {
+    fn name(&self) -> &'static str {
+        Self::NAME
+    }
+
+    fn kind(&self) -> &'static str {
+        Self::KIND
+    }
+
+    fn input_widths(&self) -> Vec<usize> {
+        vec![self.value.len(), 1, 1, 1]
+    }
+
+    fn output_widths(&self) -> Vec<usize> {
+        vec![self.value.len()]
+    }
+
+    fn update(&mut self, inputs: &[Logic], outputs: &mut [Logic]) {
+        let width = self.value.len();
+        let (data, controls) = inputs.split_at(width);
+        let [load, enable, clock] = [controls[0], controls[1], controls[2]];
+
+        if rising(&mut self.clock, clock) {
+            match control(load, true) {
+                Some(true) => {
+                    for (bit, data) in self.value.iter_mut().zip(data) {
+                        *bit = data.read();
+                    }
+                }
+                Some(false) => {}
+                None => self.value.fill(Logic::X),
+            }
+        }
+
+        match control(enable, true) {
+            Some(true) => outputs.copy_from_slice(&self.value),
+            Some(false) => outputs.fill(Logic::Z),
+            None => outputs.fill(Logic::X),
+        }
+    }
+
+    fn params(&self) -> Params {
+        Params::default().with("width", self.value.len())
+    }
+}
+
+/// Counts clock edges up or down, wrapping around.
+///
+/// Inputs are the direction (default high, counting up), the count enable
+/// (default high), an asynchronous reset (default low) and the clock. Outputs
+/// are the count and a carry which is high while counting is enabled and the
+/// next edge wraps around.
+pub struct Counter {
+    width: usize,
+    /// `None` while unknown.
+    value: Option<u64>,
+    clock: Logic,
+}
+
+impl Counter {
+    pub const NAME: &'static str = "CNT";
+    pub const KIND: &'static str = "counter";
+
+    pub fn new(width: usize) -> Counter {
+        Counter {
+            width: clamp_width(width),
+            value: Some(0),
+            clock: Logic::Z,
+        }
+    }
+
+    fn max(&self) -> u64 {
+        u64::MAX >> (64 - self.width)
+    }
+}
+
+impl DynamicGate for Counter {
+    fn name(&self) -> &'static str {
+        Self::NAME
+    }
+
+    fn kind(&self) -> &'static str {
+        Self::KIND
+    }
+
+    fn input_widths(&self) -> Vec<usize> {
+        vec![1, 1, 1, 1]
+    }
+
+    fn output_widths(&self) -> Vec<usize> {
+        vec![self.width, 1]
+    }
+
+    fn update(&mut self, inputs: &[Logic], outputs: &mut [Logic]) {
+        let up = control(inputs[0], true);
+        let enable = control(inputs[1], true);
+        let reset = control(inputs[2], false);
+
+        let edge = rising(&mut self.clock, inputs[3]);
+        match reset {
+            Some(true) => self.value = Some(0),
+            None => self.value = None,
+            Some(false) if edge => {
+                self.value = match (enable, up, self.value) {
+                    (Some(false), ..) => self.value,
+                    (Some(true), Some(true), Some(value)) => {
+                        Some(value.wrapping_add(1) & self.max())
+                    }
+                    (Some(true), Some(false), Some(value)) => {
+                        Some(value.wrapping_sub(1) & self.max())
+                    }
+                    _ => None,
+                }
+            }
+            Some(false) => {}
+        }
+
+        let (count, carry) = outputs.split_at_mut(self.width);
+        match self.value {
+            Some(value) => set_bus_value(count, value),
+            None => count.fill(Logic::X),
+        }
+        let terminal = match up {
+            Some(true) => self.value.map(|value| value == self.max()),
+            Some(false) => self.value.map(|value| value == 0),
+            None => None,
+        };
+        carry[0] = match (enable, terminal) {
+            (Some(false), _) | (_, Some(false)) => Logic::Zero,
+            (Some(true), Some(true)) => Logic::One,
+            _ => Logic::X,
+        };
+    }
+
+    fn params(&self) -> Params {
+        Params::default().with("width", self.width)
+    }
+}
+
+/// Shifts its word toward the most significant bit on a clock edge.
+///
+/// Inputs are the serial input which becomes the least significant bit, the
+/// parallel data, `load` (default low) which stores the data instead of
+/// shifting, the shift enable (default high) and the clock. Outputs are the
+/// word and the serial output, its most significant bit.
+pub struct ShiftRegister {
+    value: Vec<Logic>,
+    clock: Logic,
+}
+
+impl ShiftRegister {
+    pub const NAME: &'static str = "SHIFT";
+    pub const KIND: &'static str = "shift_register";
+
+    pub fn new(width: usize) -> ShiftRegister {
+        ShiftRegister {
+            value: vec![Logic::Zero; clamp_width(width)],
+            clock: Logic::Z,
+        }
+    }
+}
+
+impl DynamicGate for ShiftRegister {
+    fn name(&self) -> &'static str {
+        Self::NAME
+    }
+
+    fn kind(&self) -> &'static str {
+        Self::KIND
+    }
+
+    fn input_widths(&self) -> Vec<usize> {
+        vec![1, self.value.len(), 1, 1, 1]
+    }
+
+    fn output_widths(&self) -> Vec<usize> {
+        vec![self.value.len(), 1]
+    }
+
+    fn update(&mut self, inputs: &[Logic], outputs: &mut [Logic]) {
+        let width = self.value.len();
+        let serial = inputs[0];
+        let data = &inputs[1..=width];
+        let [load, enable, clock] = [inputs[width + 1], inputs[width + 2], inputs[width + 3]];
+
+        if rising(&mut self.clock, clock) {
+            match (control(load, false), control(enable, true)) {
+                (Some(true), _) => {
+                    for (bit, data) in self.value.iter_mut().zip(data) {
+                        *bit = data.read();
+                    }
+                }
+                (Some(false), Some(true)) => {
+                    self.value.rotate_right(1);
+                    self.value[0] = serial.read();
+                }
+                (Some(false), Some(false)) => {}
+                _ => self.value.fill(Logic::X),
+            }
+        }
+
+        outputs[..width].copy_from_slice(&self.value);
+        outputs[width] = self.value[width - 1];
+    }
+
+    fn params(&self) -> Params {
+        Params::default().with("width", self.value.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::gates::tests::{N, X, Y, Z};
+
+    /// Updates the gate with the clock low and then high, returns the outputs.
+    fn tick(gate: &mut impl DynamicGate, inputs: &mut [Logic]) -> Vec<Logic> {
+        let mut outputs = vec![Z; gate.output_widths().iter().sum()];
+        let clock = inputs.len() - 1;
+        for value in [N, Y] {
+            inputs[clock] = value;
+            gate.update(inputs, &mut outputs);
+        }
+        outputs
+    }
+
+    #[test]
+    fn register() {
+        let mut register = Register::new(2);
+        // data, load, enable, clock
+        let mut inputs = [Y, N, Z, Z, N];
+        assert_eq!(tick(&mut register, &mut inputs), [Y, N]);
+
+        inputs[..2].copy_from_slice(&[N, Y]);
+        inputs[2] = N;
+        assert_eq!(tick(&mut register, &mut inputs), [Y, N]);
+
+        inputs[3] = N;
+        assert_eq!(tick(&mut register, &mut inputs), [Z, Z]);
+
+        inputs[2..4].copy_from_slice(&[X, Y]);
+        assert_eq!(tick(&mut register, &mut inputs), [X, X]);
+    }
+
+    #[test]
+    fn counter() {
+        let mut counter = Counter::new(2);
+        // up, enable, reset, clock
+        let mut inputs = [Z, Z, Z, N];
+        let counts: Vec<_> = (0..4).map(|_| tick(&mut counter, &mut inputs)).collect();
+        assert_eq!(counts, [[Y, N, N], [N, Y, N], [Y, Y, Y], [N, N, N]]);
+
+        inputs[0] = N;
+        assert_eq!(tick(&mut counter, &mut inputs), [Y, Y, N]);
+
+        inputs[1] = N;
+        assert_eq!(tick(&mut counter, &mut inputs), [Y, Y, N]);
+
+        // resetting does not wait for the clock
+        inputs[2] = Y;
+        let mut outputs = [Z; 3];
+        counter.update(&inputs, &mut outputs);
+        assert_eq!(outputs, [N, N, N]);
+
+        inputs[1..3].copy_from_slice(&[X, N]);
+        assert_eq!(tick(&mut counter, &mut inputs), [X, X, X]);
+    }
+
+    #[test]
+    fn shift_register() {
+        let mut shift = ShiftRegister::new(3);
+        // serial, data, load, enable, clock
+        let mut inputs = [Y, Z, Z, Z, Z, Z, N];
+        assert_eq!(tick(&mut shift, &mut inputs), [Y, N, N, N]);
+        assert_eq!(tick(&mut shift, &mut inputs), [Y, Y, N, N]);
+
+        inputs[0] = N;
+        assert_eq!(tick(&mut shift, &mut inputs), [N, Y, Y, Y]);
+
+        inputs[5] = N;
+        assert_eq!(tick(&mut shift, &mut inputs), [N, Y, Y, Y]);
+
+        inputs[1..5].copy_from_slice(&[Y, N, Y, Y]);
+        assert_eq!(tick(&mut shift, &mut inputs), [Y, N, Y, Y]);
+    }
+}
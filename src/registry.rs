@@ -0,0 +1,463 @@
+//! Gate kinds which can be placed and loaded from circuit files, with what
+//! the editor shows about them. Crates using logic-sim as a library can
+//! register their own kinds next to the built in ones:
+//!
+//! ```
+//! use logic_sim::{
+//!     gates::{Gate, Params},
+//!     logic_simulation::BoxedGate,
+//!     registry::{GateInfo, GateRegistry, Shape},
+//!     Logic,
+//! };
+//!
+//! struct Majority;
+//!
+//! impl Gate<3, 1> for Majority {
+//!     const NAME: &'static str = "MAJ";
+//!     const KIND: &'static str = "majority";
+//!
+//!     fn update(&self, [a, b, c]: &[Logic; 3], outputs: &mut [Logic; 1]) {
+//!         outputs[0] = (*a & *b) | (*a & *c) | (*b & *c);
+//!     }
+//! }
+//!
+//! let mut registry = GateRegistry::default();
+//! registry
+//!     .register(GateInfo {
+//!         kind: Majority::KIND,
+//!         name: Majority::NAME,
+//!         category: "Custom",
+//!         inputs: &["in"],
+//!         outputs: &["out"],
+//!         icon: "2/3",
+//!         shape: Shape::Box,
+//!         factory: Box::new(|_: &Params| Ok(BoxedGate::gate(Majority))),
+//!     })
+//!     .unwrap();
+//! assert!(registry.create("majority", &Params::default()).is_some());
+//! ```
+
+use std::fmt;
+
+use crate::{
+    arithmetic::{Adder, Alu, Comparator, FullAdder, HalfAdder, Subtractor},
+    flip_flops::{DFlipFlop, DLatch, Edge, JkFlipFlop, SrLatch, TFlipFlop},
+    gates::*,
+    logic_simulation::BoxedGate,
+    memory::{Ram, Rom},
+    plexers::{Decoder, Demultiplexer, Multiplexer, PriorityEncoder},
+    registers::{Counter, Register, ShiftRegister},
+};
+
+/// Creates a gate from the parameters of a circuit file, parameters it does
+/// not know are ignored.
+pub type Factory = Box<dyn Fn(&Params) -> Result<BoxedGate, ParamError> + Send + Sync>;
+
+/// How the editor draws a gate and what clicking it does.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Shape {
+    /// Box with the name and pins on both sides.
+    #[default]
+    Box,
+    /// Box which only grows taller with more inputs, like a basic gate.
+    Tall,
+    /// Wider box with a control in the middle showing the first output,
+    /// clicking the control presses and releases the source.
+    Source,
+    /// Lamp lit by its inputs.
+    Led,
+    /// Seven segments, one input each.
+    SevenSegment,
+    /// Seven segments showing the hex digit of its four inputs.
+    HexDigit,
+}
+
+/// A gate kind and how it is shown.
+pub struct GateInfo {
+    /// See [`Gate::KIND`].
+    pub kind: &'static str,
+    /// See [`Gate::NAME`].
+    pub name: &'static str,
+    /// Heading the gate is listed under, kinds of one category are listed
+    /// together in the order they were registered.
+    pub category: &'static str,
+    /// Names of the pins. A gate with more pins than names, like a gate with
+    /// a configurable number of inputs, repeats the last name with an index
+    /// for the remaining pins.
+    pub inputs: &'static [&'static str],
+    pub outputs: &'static [&'static str],
+    /// A few characters shown next to the name.
+    pub icon: &'static str,
+    pub shape: Shape,
+    pub factory: Factory,
+}
+
+impl GateInfo {
+    /// Name of an input of a gate with `count` inputs, see [`GateInfo::inputs`].
+    pub fn input_name(&self, input: usize, count: usize) -> String {
+        pin_name(self.inputs, input, count)
+    }
+
+    /// Name of an output of a gate with `count` outputs.
+    pub fn output_name(&self, output: usize, count: usize) -> String {
+        pin_name(self.outputs, output, count)
+    }
+}
+
+impl fmt::Debug for GateInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("GateInfo")
+            .field("kind", &self.kind)
+            .field("name", &self.name)
+            .field("category", &self.category)
+            .finish_non_exhaustive()
+    }
+}
+
+fn pin_name(names: &[&str], pin: usize, count: usize) -> String {
+    match names.split_last() {
+        Some(_) if names.len() == count => names[pin].to_owned(),
+        Some((last, fixed)) => match fixed.get(pin) {
+            Some(name) => (*name).to_owned(),
+            None => format!("{last}{}", pin - fixed.len()),
+        },
+        None => pin.to_string(),
+    }
+}
+
+/// A kind which is registered already.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DuplicateKind(pub &'static str);
+
+impl fmt::Display for DuplicateKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "gate kind '{}' is already registered", self.0)
+    }
+}
+
+impl std::error::Error for DuplicateKind {}
+
+/// Gate kinds by their [`Gate::KIND`], the default registry holds all kinds
+/// built into logic-sim.
+#[derive(Debug)]
+pub struct GateRegistry {
+    gates: Vec<GateInfo>,
+}
+
+impl Default for GateRegistry {
+    fn default() -> GateRegistry {
+        let mut registry = GateRegistry { gates: Vec::new() };
+        for info in builtin() {
+            registry.register(info).expect("built in kinds are unique");
+        }
+        registry
+    }
+}
+
+impl GateRegistry {
+    pub fn register(&mut self, info: GateInfo) -> Result<(), DuplicateKind> {
+        if self.get(info.kind).is_some() {
+            return Err(DuplicateKind(info.kind));
+        }
+        self.gates.push(info);
+        Ok(())
+    }
+
+    pub fn get(&self, kind: &str) -> Option<&GateInfo> {
+        self.gates.iter().find(|info| info.kind == kind)
+    }
+
+    /// Shape of a kind, [`Shape::Box`] for unknown kinds like custom
+    /// components.
+    pub fn shape(&self, kind: &str) -> Shape {
+        self.get(kind).map_or(Shape::Box, |info| info.shape)
+    }
+
+    /// All kinds in the order they were registered.
+    pub fn iter(&self) -> impl Iterator<Item = &GateInfo> {
+        self.gates.iter()
+    }
+
+    /// Categories in the order their first kind was registered.
+    pub fn categories(&self) -> Vec<&'static str> {
+        let mut categories = Vec::new();
+        for info in &self.gates {
+            if !categories.contains(&info.category) {
+                categories.push(info.category);
+            }
+        }
+        categories
+    }
+
+    /// Creates a gate of the given kind, returns `None` for unknown kinds.
+    /// The gate is saved as that kind, whatever gate the factory made.
+    pub fn create(&self, kind: &str, params: &Params) -> Option<Result<BoxedGate, ParamError>> {
+        self.get(kind)
+            .map(|info| Ok((info.factory)(params)?.with_kind(info.kind)))
+    }
+}
+
+/// Two input gates stay fixed size, so circuits without the `inputs`
+/// parameter load like before.
+fn multi_input<G: MultiInput + 'static>(gate: G, params: &Params) -> Result<BoxedGate, ParamError> {
+    Ok(match params.get_or("inputs", 2)? {
+        2 => BoxedGate::gate(gate),
+        inputs => BoxedGate::dynamic(Wide::new(gate, inputs)),
+    })
+}
+
+fn edge(params: &Params) -> Result<Edge, ParamError> {
+    params.get_or("edge", Edge::Rising)
+}
+
+#[rustfmt::skip]
+fn builtin() -> Vec<GateInfo> {
+    fn info(
+        kind: &'static str,
+        name: &'static str,
+        category: &'static str,
+        inputs: &'static [&'static str],
+        outputs: &'static [&'static str],
+        icon: &'static str,
+        factory: impl Fn(&Params) -> Result<BoxedGate, ParamError> + Send + Sync + 'static,
+    ) -> GateInfo {
+        GateInfo { kind, name, category, inputs, outputs, icon, shape: Shape::Box, factory: Box::new(factory) }
+    }
+
+    const GATES: &str = "Gates";
+    const IO: &str = "Inputs and outputs";
+    const WIRING: &str = "Wiring";
+    const ARITHMETIC: &str = "Arithmetic";
+    const PLEXERS: &str = "Plexers";
+    const FLIP_FLOPS: &str = "Flip-flops";
+    const MEMORY: &str = "Memory";
+
+    vec![
+        GateInfo { shape: Shape::Tall, ..info(And::KIND, And::NAME, GATES, &["in"], &["out"], "&", |params| multi_input(And, params)) },
+        GateInfo { shape: Shape::Tall, ..info(Nand::KIND, Nand::NAME, GATES, &["in"], &["out"], "!&", |params| multi_input(Nand, params)) },
+        GateInfo { shape: Shape::Tall, ..info(Or::KIND, Or::NAME, GATES, &["in"], &["out"], "|", |params| multi_input(Or, params)) },
+        GateInfo { shape: Shape::Tall, ..info(Nor::KIND, Nor::NAME, GATES, &["in"], &["out"], "!|", |params| multi_input(Nor, params)) },
+        GateInfo { shape: Shape::Tall, ..info(Xor::KIND, Xor::NAME, GATES, &["in"], &["out"], "^", |params| multi_input(Xor, params)) },
+        GateInfo { shape: Shape::Tall, ..info(Xnor::KIND, Xnor::NAME, GATES, &["in"], &["out"], "!^", |params| multi_input(Xnor, params)) },
+        info(Yes::KIND, Yes::NAME, GATES, &["in"], &["out"], "=", |_| Ok(BoxedGate::gate(Yes))),
+        info(Not::KIND, Not::NAME, GATES, &["in"], &["out"], "!", |_| Ok(BoxedGate::gate(Not))),
+        info(TriState::KIND, TriState::NAME, GATES, &["in", "enable"], &["out"], "z", |_| {
+            Ok(BoxedGate::gate(TriState))
+        }),
+        GateInfo { shape: Shape::Source, ..info(Switch::KIND, Switch::NAME, IO, &[], &["out"], "0/1", |params| {
+            Ok(BoxedGate::source(Switch::new(params.get_or("on", false)?)))
+        }) },
+        GateInfo { shape: Shape::Source, ..info(Button::KIND, Button::NAME, IO, &[], &["out"], "[ ]", |_| {
+            Ok(BoxedGate::source(Button::default()))
+        }) },
+        GateInfo { shape: Shape::Source, ..info(Clock::KIND, Clock::NAME, IO, &[], &["out"], "_|~", |params| {
+            Ok(BoxedGate::source(Clock::new(
+                params.get_or("period", 2)?,
+                params.get_or("duty_cycle", 0.5)?,
+            )))
+        }) },
+        info(Constant::KIND, Constant::NAME, IO, &[], &["out"], "1", |params| {
+            Ok(BoxedGate::gate(Constant::new(params.get_or("value", false)?)))
+        }),
+        GateInfo { shape: Shape::Led, ..info(Led::KIND, Led::NAME, IO, &["in"], &[], "()", |_| Ok(BoxedGate::gate(Led))) },
+        GateInfo { shape: Shape::SevenSegment, ..info(SevenSegment::KIND, SevenSegment::NAME, IO, &["a", "b", "c", "d", "e", "f", "g"], &[], "8", |_| {
+            Ok(BoxedGate::gate(SevenSegment))
+        }) },
+        GateInfo { shape: Shape::HexDigit, ..info(HexDigit::KIND, HexDigit::NAME, IO, &["bit"], &[], "0x", |_| Ok(BoxedGate::gate(HexDigit))) },
+        info(Splitter::KIND, Splitter::NAME, WIRING, &["bus"], &["bit"], "=<", |params| {
+            Ok(BoxedGate::dynamic(Splitter::new(params.get_or("width", 8)?)))
+        }),
+        info(Merger::KIND, Merger::NAME, WIRING, &["bit"], &["bus"], ">=", |params| {
+            Ok(BoxedGate::dynamic(Merger::new(params.get_or("width", 8)?)))
+        }),
+        info(HalfAdder::KIND, HalfAdder::NAME, ARITHMETIC, &["a", "b"], &["sum", "carry"], "+", |_| {
+            Ok(BoxedGate::gate(HalfAdder))
+        }),
+        info(FullAdder::KIND, FullAdder::NAME, ARITHMETIC, &["a", "b", "carry"], &["sum", "carry"], "+", |_| {
+            Ok(BoxedGate::gate(FullAdder))
+        }),
+        info(Adder::KIND, Adder::NAME, ARITHMETIC, &["a", "b", "carry"], &["sum", "carry"], "+", |params| {
+            Ok(BoxedGate::dynamic(Adder::new(params.get_or("width", 8)?)))
+        }),
+        info(Subtractor::KIND, Subtractor::NAME, ARITHMETIC, &["a", "b", "borrow"], &["difference", "borrow"], "-", |params| {
+            Ok(BoxedGate::dynamic(Subtractor::new(params.get_or("width", 8)?)))
+        }),
+        info(Alu::KIND, Alu::NAME, ARITHMETIC, &["a", "b", "opcode"], &["result", "carry", "zero"], "+-&", |params| {
+            Ok(BoxedGate::dynamic(Alu::new(params.get_or("width", 8)?)))
+        }),
+        info(Comparator::KIND, Comparator::NAME, ARITHMETIC, &["a", "b"], &["less", "equal", "greater"], "<=>", |params| {
+            Ok(BoxedGate::dynamic(Comparator::new(params.get_or("width", 8)?)))
+        }),
+        info(Multiplexer::KIND, Multiplexer::NAME, PLEXERS, &["select", "in"], &["out"], ">-", |params| {
+            Ok(BoxedGate::dynamic(Multiplexer::new(
+                params.get_or("inputs", 2)?,
+                params.get_or("width", 1)?,
+            )))
+        }),
+        info(Demultiplexer::KIND, Demultiplexer::NAME, PLEXERS, &["select", "in"], &["out"], "-<", |params| {
+            Ok(BoxedGate::dynamic(Demultiplexer::new(
+                params.get_or("outputs", 2)?,
+                params.get_or("width", 1)?,
+            )))
+        }),
+        info(Decoder::KIND, Decoder::NAME, PLEXERS, &["select"], &["out"], "1ofN", |params| {
+            Ok(BoxedGate::dynamic(Decoder::new(params.get_or("outputs", 2)?)))
+        }),
+        info(PriorityEncoder::KIND, PriorityEncoder::NAME, PLEXERS, &["in"], &["index", "valid"], "N>i", |params| {
+            Ok(BoxedGate::dynamic(PriorityEncoder::new(params.get_or("inputs", 2)?)))
+        }),
+        info(SrLatch::KIND, SrLatch::NAME, FLIP_FLOPS, &["set", "reset"], &["q", "not_q"], "SR", |_| {
+            Ok(BoxedGate::dynamic(SrLatch::new()))
+        }),
+        info(DLatch::KIND, DLatch::NAME, FLIP_FLOPS, &["d", "enable"], &["q", "not_q"], "D", |_| {
+            Ok(BoxedGate::dynamic(DLatch::new()))
+        }),
+        info(DFlipFlop::KIND, DFlipFlop::NAME, FLIP_FLOPS, &["d", "clock"], &["q", "not_q"], "DF>", |params| {
+            Ok(BoxedGate::dynamic(DFlipFlop::new(edge(params)?)))
+        }),
+        info(JkFlipFlop::KIND, JkFlipFlop::NAME, FLIP_FLOPS, &["j", "k", "clock"], &["q", "not_q"], "JK>", |params| {
+            Ok(BoxedGate::dynamic(JkFlipFlop::new(edge(params)?)))
+        }),
+        info(TFlipFlop::KIND, TFlipFlop::NAME, FLIP_FLOPS, &["t", "clock"], &["q", "not_q"], "T>", |params| {
+            Ok(BoxedGate::dynamic(TFlipFlop::new(edge(params)?)))
+        }),
+        info(Register::KIND, Register::NAME, MEMORY, &["data", "load", "enable", "clock"], &["q"], "D>", |params| {
+            Ok(BoxedGate::dynamic(Register::new(params.get_or("width", 8)?)))
+        }),
+        info(Counter::KIND, Counter::NAME, MEMORY, &["up", "enable", "reset", "clock"], &["count", "carry"], "+1", |params| {
+            Ok(BoxedGate::dynamic(Counter::new(params.get_or("width", 8)?)))
+        }),
+        info(ShiftRegister::KIND, ShiftRegister::NAME, MEMORY, &["serial", "data", "load", "enable", "clock"], &["q", "serial"], ">>", |params| {
+            Ok(BoxedGate::dynamic(ShiftRegister::new(params.get_or("width", 8)?)))
+        }),
+        info(Rom::KIND, Rom::NAME, MEMORY, &["address"], &["data"], "[#]", |params| {
+            let address_width = params.get_or("address_width", 8)?;
+            let data_width = params.get_or("data_width", 8)?;
+            let rom = match params.get("file") {
+                Some(file) => Rom::load(address_width, data_width, file).map_err(|_| ParamError {
+                    key: "file".to_owned(),
+                    value: file.to_owned(),
+                })?,
+                None => Rom::new(address_width, data_width, &[]),
+            };
+            Ok(BoxedGate::dynamic(rom))
+        }),
+        info(Ram::KIND, Ram::NAME, MEMORY, &["address", "data", "write", "clock"], &["data"], "[#]", |params| {
+            Ok(BoxedGate::dynamic(Ram::new(
+                params.get_or("address_width", 8)?,
+                params.get_or("data_width", 8)?,
+            )))
+        }),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        circuit_file::{Circuit, GateEntry},
+        component,
+        logic_simulation::LogicSimulation,
+    };
+
+    #[test]
+    fn kinds_are_unique() {
+        let gates = builtin();
+        for (index, info) in gates.iter().enumerate() {
+            assert!(
+                gates[index + 1..]
+                    .iter()
+                    .all(|other| other.kind != info.kind),
+                "duplicate kind {}",
+                info.kind
+            );
+        }
+    }
+
+    #[test]
+    fn pins_are_named() {
+        // every built in gate has names for its default pins
+        let registry = GateRegistry::default();
+        let mut sim = LogicSimulation::new();
+        for info in registry.iter() {
+            let gate = registry
+                .create(info.kind, &Params::default())
+                .unwrap()
+                .unwrap();
+            let id = sim.add_boxed_gate(gate);
+            let (inputs, outputs) = sim.get_pin_widths(id).unwrap();
+            assert!(
+                info.inputs.len() <= inputs.len() && info.outputs.len() <= outputs.len(),
+                "{}",
+                info.kind
+            );
+        }
+
+        let mux = registry.get("multiplexer").unwrap();
+        let names: Vec<_> = (0..3).map(|pin| mux.input_name(pin, 3)).collect();
+        assert_eq!(names, ["select", "in0", "in1"]);
+        assert_eq!(registry.get("and").unwrap().output_name(0, 1), "out");
+        assert_eq!(registry.get("counter").unwrap().output_name(1, 2), "carry");
+    }
+
+    #[test]
+    fn create() {
+        let registry = GateRegistry::default();
+        assert!(registry.create("and", &Params::default()).unwrap().is_ok());
+        assert!(registry
+            .create("flux_capacitor", &Params::default())
+            .is_none());
+        assert_eq!(registry.shape("clock"), Shape::Source);
+        assert_eq!(registry.shape("flux_capacitor"), Shape::Box);
+
+        let params = Params::default().with("inputs", 5);
+        let mut sim = LogicSimulation::new();
+        let xor = sim.add_boxed_gate(registry.create("xor", &params).unwrap().unwrap());
+        assert_eq!(sim.get_pin_widths(xor).unwrap().0.len(), 5);
+        assert_eq!(sim.get_gate_params(xor).unwrap(), params);
+
+        let params = Params::default().with("period", "soon");
+        assert!(registry.create("clock", &params).unwrap().is_err());
+    }
+
+    #[test]
+    fn register() {
+        let delay = 3;
+        let info = |kind| GateInfo {
+            kind,
+            name: "AND",
+            category: "Custom",
+            inputs: &["in"],
+            outputs: &["out"],
+            icon: "&",
+            shape: Shape::Tall,
+            factory: Box::new(move |_: &Params| Ok(BoxedGate::gate(And).with_delay(delay))),
+        };
+
+        let mut registry = GateRegistry::default();
+        assert_eq!(registry.register(info("and")), Err(DuplicateKind("and")));
+
+        registry.register(info("and_too")).unwrap();
+        let mut sim = LogicSimulation::new();
+        let gate = registry.create("and_too", &Params::default()).unwrap();
+        let gate = sim.add_boxed_gate(gate.unwrap());
+
+        // saved and loaded again as the registered kind
+        let circuit = Circuit {
+            gates: vec![GateEntry {
+                id: 0,
+                kind: sim.get_gate_kind(gate).unwrap().to_owned(),
+                pos: (0., 0.),
+                params: sim.get_gate_params(gate).unwrap(),
+            }],
+            ..Circuit::default()
+        };
+        let circuit = Circuit::parse(&circuit.to_string()).unwrap();
+        let mut sim = LogicSimulation::new();
+        let ids = component::instantiate(&circuit, &registry, &[], &mut sim).unwrap();
+        assert_eq!(sim.get_gate_kind(ids[&0]).unwrap(), "and_too");
+        assert_eq!(sim.get_gate_delay(ids[&0]).unwrap(), 3);
+        assert_eq!(registry.categories().last(), Some(&"Custom"));
+        assert_eq!(registry.categories()[0], "Gates");
+    }
+}
@@ -0,0 +1,149 @@
+//! Circuits built from boolean expressions, laid out left to right: a switch
+//! for every variable and a constant for every constant, a column of gates for
+//! every level of the expressions and a led for every expression.
+
+use std::collections::HashMap;
+
+use crate::{
+    circuit_file::{Circuit, ConnectionEntry, GateEntry},
+    expression::Expr,
+    gates::{And, Constant, Gate, Led, Not, Or, Params, Source, Switch, Xor},
+};
+
+/// Distance between the columns and between the rows of the layout.
+pub const SPACING: (f32, f32) = (150., 80.);
+
+/// Builds a circuit computing all expressions at once. Switches are ordered
+/// by variable name and leds like the expressions, so both match the columns
+/// of the circuit's truth table. Equal subexpressions share their gates and
+/// constants become [`Constant`] gates, which are not inputs.
+///
+/// Pin offsets of the connections are left at zero, they depend on how the
+/// gates are drawn.
+pub fn synthesize(exprs: &[Expr]) -> Circuit {
+    let mut builder = Builder {
+        circuit: Circuit::default(),
+        gates: HashMap::new(),
+        rows: Vec::new(),
+    };
+
+    let mut vars: Vec<String> = exprs.iter().flat_map(Expr::vars).collect();
+    vars.sort();
+    vars.dedup();
+    for var in vars {
+        builder.gate(&Expr::Var(var));
+    }
+
+    let outputs: Vec<_> = exprs.iter().map(|expr| builder.gate(expr)).collect();
+    let column = builder.rows.len();
+    for (output, _) in outputs {
+        let led = builder.add(Led::KIND, Params::default(), column);
+        builder.connect(output, led, 0);
+    }
+
+    builder.circuit
+}
+
+struct Builder {
+    circuit: Circuit,
+    /// Gate id and column of every subexpression built so far.
+    gates: HashMap<Expr, (usize, usize)>,
+    /// Gates in each column.
+    rows: Vec<usize>,
+}
+
+impl Builder {
+    fn add(&mut self, kind: &str, params: Params, column: usize) -> usize {
+        if self.rows.len() <= column {
+            self.rows.resize(column + 1, 0);
+        }
+        let row = self.rows[column];
+        self.rows[column] += 1;
+
+        let id = self.circuit.gates.len();
+        self.circuit.gates.push(GateEntry {
+            id,
+            kind: kind.to_owned(),
+            pos: (column as f32 * SPACING.0, row as f32 * SPACING.1),
+            params,
+        });
+        id
+    }
+
+    fn connect(&mut self, output: usize, input: usize, pin: usize) {
+        self.circuit.connections.push(ConnectionEntry {
+            output: (output, 0, (0., 0.)),
+            input: (input, pin, (0., 0.)),
+        });
+    }
+
+    /// Gate id and column of the gate computing the expression.
+    fn gate(&mut self, expr: &Expr) -> (usize, usize) {
+        if let Some(gate) = self.gates.get(expr) {
+            return *gate;
+        }
+
+        let gate = match expr {
+            Expr::Const(value) => {
+                let params = Params::default().with("value", value);
+                (self.add(Constant::KIND, params, 0), 0)
+            }
+            Expr::Var(_) => (self.add(Switch::KIND, Params::default(), 0), 0),
+            Expr::Not(a) => self.operation(Not::KIND, &[a]),
+            Expr::And(a, b) => self.operation(And::KIND, &[a, b]),
+            Expr::Or(a, b) => self.operation(Or::KIND, &[a, b]),
+            Expr::Xor(a, b) => self.operation(Xor::KIND, &[a, b]),
+        };
+        self.gates.insert(expr.clone(), gate);
+        gate
+    }
+
+    /// Adds a gate in the column after its last operand.
+    fn operation(&mut self, kind: &str, operands: &[&Expr]) -> (usize, usize) {
+        let operands: Vec<_> = operands.iter().map(|operand| self.gate(operand)).collect();
+        let column = operands.iter().map(|(_, column)| column + 1).max().unwrap();
+
+        let id = self.add(kind, Params::default(), column);
+        for (pin, (operand, _)) in operands.into_iter().enumerate() {
+            self.connect(operand, id, pin);
+        }
+        (id, column)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{registry::GateRegistry, truth_table::TruthTable};
+
+    #[test]
+    fn synthesize() {
+        let exprs: Vec<Expr> = ["(A & !B) | C", "!B ^ 1"]
+            .iter()
+            .map(|source| source.parse().unwrap())
+            .collect();
+        let circuit = super::synthesize(&exprs);
+
+        // A, B, C and the constant, then the shared !B
+        let kinds: Vec<_> = circuit
+            .gates
+            .iter()
+            .map(|gate| gate.kind.as_str())
+            .collect();
+        #[rustfmt::skip]
+        assert_eq!(kinds, [
+            "switch", "switch", "switch", "not", "and", "or", "constant", "xor", "led", "led",
+        ]);
+        assert_eq!(circuit.gates[5].pos, (3. * SPACING.0, 0.));
+        assert_eq!(circuit.gates[9].pos, (4. * SPACING.0, SPACING.1));
+
+        // the constant is not an input of the table
+        let table = TruthTable::generate(&circuit, &GateRegistry::default()).unwrap();
+        assert_eq!(table.inputs.len(), 3);
+        let vars: Vec<_> = ["A", "B", "C"].map(str::to_owned).into();
+        for (row, (_, outputs)) in table.rows.iter().enumerate() {
+            let expected = [&exprs[0], &exprs[1]].map(|expr| expr.column(&vars)[row]);
+            assert_eq!(outputs[..], expected);
+        }
+    }
+}
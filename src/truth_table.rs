@@ -0,0 +1,189 @@
+//! Truth tables of circuits, their inputs are the switches and buttons and
+//! their outputs the leds, ordered top to bottom like the pins of a custom
+//! component.
+
+use std::fmt;
+
+use crate::{
+    circuit_file::{Circuit, LoadError},
+    component,
+    logic::Logic,
+    logic_simulation::{LogicSimulation, Oscillation},
+    registry::GateRegistry,
+};
+
+/// Most inputs a table can have, the table doubles in size with every input.
+pub const MAX_INPUTS: usize = 16;
+
+/// Name of the column of an input, `a` to `z` and `in26` onwards.
+pub fn input_name(index: usize) -> String {
+    match u8::try_from(index) {
+        Ok(index @ 0..=25) => char::from(b'a' + index).to_string(),
+        _ => format!("in{index}"),
+    }
+}
+
+#[derive(Debug)]
+pub enum TruthTableError {
+    TooManyInputs(usize),
+    Load(LoadError),
+    /// The circuit did not become stable for the inputs of the row.
+    Oscillation {
+        row: usize,
+        oscillation: Oscillation,
+    },
+}
+
+impl fmt::Display for TruthTableError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TruthTableError::TooManyInputs(inputs) => {
+                write!(f, "{inputs} inputs, truth tables have at most {MAX_INPUTS}")
+            }
+            TruthTableError::Load(err) => write!(f, "invalid circuit: {err}"),
+            TruthTableError::Oscillation { row, oscillation } => {
+                write!(f, "row {row}: {oscillation}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for TruthTableError {}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TruthTable {
+    /// Names of the columns, inputs are named `a`, `b`, ... and outputs `y0`,
+    /// `y1`, ...
+    pub inputs: Vec<String>,
+    pub outputs: Vec<String>,
+    /// Every combination of inputs counting up from all low, the first input
+    /// is the most significant bit.
+    pub rows: Vec<(Vec<Logic>, Vec<Logic>)>,
+}
+
+impl TruthTable {
+    /// Simulates every combination of inputs in a fresh copy of the circuit
+    /// until it is stable, so rows do not depend on each other.
+    pub fn generate(
+        circuit: &Circuit,
+        registry: &GateRegistry,
+    ) -> Result<TruthTable, TruthTableError> {
+        let (inputs, outputs) = component::pins(circuit);
+        if inputs.len() > MAX_INPUTS {
+            return Err(TruthTableError::TooManyInputs(inputs.len()));
+        }
+
+        let mut rows = Vec::with_capacity(1 << inputs.len());
+        for row in 0..1usize << inputs.len() {
+            let mut sim = LogicSimulation::new();
+            let ids = component::instantiate(circuit, registry, &circuit.components, &mut sim)
+                .map_err(TruthTableError::Load)?;
+
+            let values: Vec<bool> = (0..inputs.len())
+                .map(|bit| row >> (inputs.len() - 1 - bit) & 1 == 1)
+                .collect();
+            component::drive_inputs(&mut sim, &ids, &inputs, &values);
+
+            sim.settle(component::MAX_SETTLE_TICKS)
+                .map_err(|oscillation| TruthTableError::Oscillation { row, oscillation })?;
+
+            rows.push((
+                values.into_iter().map(Logic::from).collect(),
+                component::read_outputs(&sim, &ids, &outputs),
+            ));
+        }
+
+        Ok(TruthTable {
+            inputs: (0..inputs.len()).map(input_name).collect(),
+            outputs: (0..outputs.len())
+                .map(|output| format!("y{output}"))
+                .collect(),
+            rows,
+        })
+    }
+
+    fn header(&self) -> impl Iterator<Item = &str> {
+        self.inputs.iter().chain(&self.outputs).map(String::as_str)
+    }
+
+    fn cells(row: &(Vec<Logic>, Vec<Logic>)) -> impl Iterator<Item = String> + '_ {
+        row.0.iter().chain(&row.1).map(Logic::to_string)
+    }
+
+    pub fn to_csv(&self) -> String {
+        let mut csv = self.header().collect::<Vec<_>>().join(",");
+        csv.push('\n');
+        for row in &self.rows {
+            csv.push_str(&Self::cells(row).collect::<Vec<_>>().join(","));
+            csv.push('\n');
+        }
+        csv
+    }
+
+    pub fn to_markdown(&self) -> String {
+        let line = |cells: Vec<String>| format!("| {} |\n", cells.join(" | "));
+
+        let mut markdown = line(self.header().map(str::to_owned).collect());
+        markdown.push_str(&line(self.header().map(|_| "---".to_owned()).collect()));
+        for row in &self.rows {
+            markdown.push_str(&line(Self::cells(row).collect()));
+        }
+        markdown
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        component::tests::HALF_ADDER,
+        gates::tests::{N, Y},
+    };
+
+    #[test]
+    fn half_adder() {
+        let table = TruthTable::generate(
+            &Circuit::parse(HALF_ADDER).unwrap(),
+            &GateRegistry::default(),
+        )
+        .unwrap();
+
+        assert_eq!(table.inputs, ["a", "b"]);
+        assert_eq!(table.outputs, ["y0", "y1"]);
+        #[rustfmt::skip]
+        assert_eq!(table.rows, [
+            (vec![N, N], vec![N, N]),
+            (vec![N, Y], vec![Y, N]),
+            (vec![Y, N], vec![Y, N]),
+            (vec![Y, Y], vec![N, Y]),
+        ]);
+
+        assert_eq!(
+            table.to_csv(),
+            "a,b,y0,y1\n0,0,0,0\n0,1,1,0\n1,0,1,0\n1,1,0,1\n"
+        );
+        assert_eq!(
+            table.to_markdown().lines().take(3).collect::<Vec<_>>(),
+            [
+                "| a | b | y0 | y1 |",
+                "| --- | --- | --- | --- |",
+                "| 0 | 0 | 0 | 0 |"
+            ]
+        );
+    }
+
+    #[test]
+    fn oscillation() {
+        // a clock never settles
+        let source = "\
+logic-sim 3
+gate 0 clock 0 0 period=2 duty_cycle=0.5
+gate 1 led 100 0
+connection 0 0 0 0 1 0 0 0
+";
+        assert!(matches!(
+            TruthTable::generate(&Circuit::parse(source).unwrap(), &GateRegistry::default()),
+            Err(TruthTableError::Oscillation { row: 0, .. })
+        ));
+    }
+}
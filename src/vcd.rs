@@ -0,0 +1,320 @@
+//! Value change dumps (IEEE 1364 VCD) of the probes of a simulation, which
+//! waveform viewers like GTKWave can open. Inside the top `logic_sim` scope
+//! every probed gate kind gets a scope holding a scope for each probed gate of
+//! that kind, like `logic_sim.led.led4`. Variables are named after the probed
+//! pins, see [`GateInfo::inputs`]. A tick lasts one nanosecond.
+//!
+//! Only the history the probes still keep is dumped, see [`HISTORY`]. Once a
+//! probe dropped its oldest changes, the dump starts with the oldest change
+//! it kept and says so in a comment.
+//!
+//! [`GateInfo::inputs`]: crate::registry::GateInfo::inputs
+
+use std::io::{self, Write};
+
+use crate::{
+    logic::Logic,
+    logic_simulation::LogicSimulation,
+    probe::{Probe, ProbePin, HISTORY},
+    registry::GateRegistry,
+};
+
+/// Writes the history of all probes, `scope` names the scope of each probed
+/// gate by its id and should give a name without whitespace. Pins are named
+/// by the registry, pins of kinds it does not know `in0`, `out0` and so on.
+pub fn write(
+    sim: &LogicSimulation,
+    registry: &GateRegistry,
+    scope: &impl Fn(usize) -> String,
+    out: &mut impl Write,
+) -> io::Result<()> {
+    let kind = |probe: &Probe| sim.get_gate_kind(probe.pin().gate()).unwrap_or_default();
+    let mut probes: Vec<_> = sim.probes().map(|(_, probe)| probe).collect();
+    probes.sort_by_key(|probe| (kind(probe), probe.pin().gate()));
+
+    writeln!(out, "$version logic-sim {} $end", env!("CARGO_PKG_VERSION"))?;
+    writeln!(out, "$timescale 1ns $end")?;
+
+    // probes added later are unknown until they were added, while the history
+    // dropped by full probes is left out
+    let first = |probe: &&Probe| probe.changes().next().map(|(time, _)| time);
+    let dropped = probes
+        .iter()
+        .filter(|probe| probe.changes().count() == HISTORY)
+        .filter_map(first)
+        .max();
+    let start = dropped
+        .or_else(|| probes.iter().filter_map(first).min())
+        .unwrap_or(sim.time());
+    if dropped.is_some() {
+        writeln!(out, "$comment changes before #{start} were dropped $end")?;
+    }
+    writeln!(out, "$scope module logic_sim $end")?;
+    let mut scoped: Option<(&str, usize)> = None;
+    for (index, probe) in probes.iter().enumerate() {
+        let (kind, gate) = (kind(probe), probe.pin().gate());
+        if scoped.map(|(_, scoped)| scoped) != Some(gate) {
+            if let Some((scoped_kind, _)) = scoped {
+                writeln!(out, "$upscope $end")?;
+                if scoped_kind != kind {
+                    writeln!(out, "$upscope $end")?;
+                }
+            }
+            if scoped.map(|(scoped_kind, _)| scoped_kind) != Some(kind) {
+                writeln!(out, "$scope module {kind} $end")?;
+            }
+            writeln!(out, "$scope module {} $end", scope(gate))?;
+            scoped = Some((kind, gate));
+        }
+
+        let width = probe.changes().next().map_or(1, |(_, value)| value.len());
+        let name = pin_name(sim, registry, probe.pin());
+        writeln!(out, "$var wire {width} {} {name} $end", code(index))?;
+    }
+    if scoped.is_some() {
+        writeln!(out, "$upscope $end")?;
+        writeln!(out, "$upscope $end")?;
+    }
+    writeln!(out, "$upscope $end")?;
+    writeln!(out, "$enddefinitions $end")?;
+
+    writeln!(out, "#{start}")?;
+    writeln!(out, "$dumpvars")?;
+    let mut written: Vec<Vec<Logic>> = probes
+        .iter()
+        .map(|probe| match probe.value_at(start) {
+            Some(value) => value.to_vec(),
+            None => vec![Logic::X; probe.changes().next().map_or(1, |(_, value)| value.len())],
+        })
+        .collect();
+    for (index, value) in written.iter().enumerate() {
+        write_value(out, value, &code(index))?;
+    }
+    writeln!(out, "$end")?;
+
+    // all later changes ordered by time
+    let mut changes: Vec<_> = probes
+        .iter()
+        .enumerate()
+        .flat_map(|(index, probe)| {
+            probe
+                .changes()
+                .filter(move |(time, _)| *time > start)
+                .map(move |(time, value)| (time, index, value))
+        })
+        .collect();
+    changes.sort_by_key(|(time, index, _)| (*time, *index));
+
+    let mut time = start;
+    for (tick, index, value) in changes {
+        if written[index] == value {
+            continue;
+        }
+        written[index] = value.to_vec();
+        if time != tick {
+            writeln!(out, "#{tick}")?;
+            time = tick;
+        }
+        write_value(out, value, &code(index))?;
+    }
+    writeln!(out, "#{}", sim.time())
+}
+
+/// Name of the pin from the registry. An input and an output of the same
+/// name, like the carry of a full adder, get `_in` and `_out` appended so
+/// both variables can be told apart.
+fn pin_name(sim: &LogicSimulation, registry: &GateRegistry, pin: ProbePin) -> String {
+    let gate = pin.gate();
+    let info = sim
+        .get_gate_kind(gate)
+        .ok()
+        .and_then(|kind| registry.get(kind));
+    let Some(info) = info else {
+        return match pin {
+            ProbePin::Input { input, .. } => format!("in{input}"),
+            ProbePin::Output { output, .. } => format!("out{output}"),
+        };
+    };
+
+    let (inputs, outputs) = sim.get_pin_widths(gate).unwrap_or_default();
+    let inputs: Vec<_> = (0..inputs.len())
+        .map(|input| info.input_name(input, inputs.len()))
+        .collect();
+    let outputs: Vec<_> = (0..outputs.len())
+        .map(|output| info.output_name(output, outputs.len()))
+        .collect();
+    match pin {
+        ProbePin::Input { input, .. } if outputs.contains(&inputs[input]) => {
+            format!("{}_in", inputs[input])
+        }
+        ProbePin::Input { input, .. } => inputs[input].clone(),
+        ProbePin::Output { output, .. } if inputs.contains(&outputs[output]) => {
+            format!("{}_out", outputs[output])
+        }
+        ProbePin::Output { output, .. } => outputs[output].clone(),
+    }
+}
+
+fn write_value(out: &mut impl Write, value: &[Logic], code: &str) -> io::Result<()> {
+    let bit = |bit: &Logic| match bit {
+        Logic::Zero => '0',
+        Logic::One => '1',
+        Logic::X => 'x',
+        Logic::Z => 'z',
+    };
+
+    match value {
+        [single] => writeln!(out, "{}{code}", bit(single)),
+        // the first bit of a bus is the least significant one
+        bus => writeln!(
+            out,
+            "b{} {code}",
+            bus.iter().rev().map(bit).collect::<String>()
+        ),
+    }
+}
+
+/// Identifier code of a variable, made of the printable ASCII characters.
+fn code(mut index: usize) -> String {
+    let mut code = String::new();
+    loop {
+        code.push(char::from(b'!' + (index % 94) as u8));
+        index /= 94;
+        if index == 0 {
+            return code;
+        }
+        index -= 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        arithmetic::FullAdder,
+        gates::{Not, Splitter, Switch},
+        logic_simulation::BoxedGate,
+    };
+
+    #[test]
+    fn write() {
+        let mut sim = LogicSimulation::new();
+        let switch = sim.add_source(Switch::new(false));
+        let not = sim.add_gate(Not);
+        let splitter = sim.add_boxed_gate(BoxedGate::dynamic(Splitter::new(2)));
+        let adder = sim.add_gate(FullAdder);
+        sim.add_connection(switch, 0, not, 0).unwrap();
+        for pin in [
+            ProbePin::Output {
+                gate: not,
+                output: 0,
+            },
+            ProbePin::Input {
+                gate: not,
+                input: 0,
+            },
+            ProbePin::Input {
+                gate: splitter,
+                input: 0,
+            },
+        ] {
+            sim.add_probe(pin).unwrap();
+        }
+        sim.simulate();
+        // unknown until it is added
+        let carry = ProbePin::Output {
+            gate: adder,
+            output: 1,
+        };
+        sim.add_probe(carry).unwrap();
+        for _ in 0..2 {
+            sim.simulate();
+        }
+
+        let mut vcd = Vec::new();
+        let registry = GateRegistry::default();
+        super::write(&sim, &registry, &|id| format!("gate{id}"), &mut vcd).unwrap();
+        let vcd = String::from_utf8(vcd).unwrap();
+        let expected = "\
+$scope module logic_sim $end
+$scope module full_adder $end
+$scope module gate3 $end
+$var wire 1 ! carry_out $end
+$upscope $end
+$upscope $end
+$scope module not $end
+$scope module gate1 $end
+$var wire 1 \" out $end
+$var wire 1 # in $end
+$upscope $end
+$upscope $end
+$scope module splitter $end
+$scope module gate2 $end
+$var wire 2 $ bus $end
+$upscope $end
+$upscope $end
+$upscope $end
+$enddefinitions $end
+#0
+$dumpvars
+x!
+x\"
+z#
+bzz $
+$end
+#1
+x#
+#2
+1\"
+0#
+#3
+";
+        assert!(vcd.ends_with(expected), "{vcd}");
+    }
+
+    #[test]
+    fn dropped_history() {
+        let mut sim = LogicSimulation::new();
+        let switch = sim.add_source(Switch::new(false));
+        let late = sim.add_source(Switch::new(false));
+        sim.add_probe(ProbePin::Output {
+            gate: switch,
+            output: 0,
+        })
+        .unwrap();
+        for _ in 0..HISTORY + 10 {
+            sim.press(switch).unwrap();
+            sim.simulate();
+        }
+        sim.add_probe(ProbePin::Output {
+            gate: late,
+            output: 0,
+        })
+        .unwrap();
+        sim.simulate();
+
+        let mut vcd = Vec::new();
+        let registry = GateRegistry::default();
+        super::write(&sim, &registry, &|id| format!("gate{id}"), &mut vcd).unwrap();
+        let vcd = String::from_utf8(vcd).unwrap();
+
+        let start = sim.time() - HISTORY as u64;
+        let comment = format!("$comment changes before #{start} were dropped $end");
+        assert!(vcd.contains(&comment), "{vcd}");
+        let dump = vcd.split("$enddefinitions $end").nth(1).unwrap();
+        assert!(
+            dump.starts_with(&format!("\n#{start}\n$dumpvars\n")),
+            "{dump}"
+        );
+        // only the probe added last starts out unknown, and only once
+        assert_eq!(dump.matches('x').count(), 1, "{dump}");
+    }
+
+    #[test]
+    fn codes() {
+        assert_eq!(code(0), "!");
+        assert_eq!(code(93), "~");
+        assert_eq!(code(94), "!!");
+        assert_eq!(code(94 + 94 * 94), "!!!");
+    }
+}